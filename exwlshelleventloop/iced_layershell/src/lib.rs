@@ -42,3 +42,5 @@ pub use build_pattern::application;
 pub use build_pattern::daemon;
 
 pub use settings::Settings;
+
+pub use multi_window::redraw_only;
@@ -38,7 +38,8 @@ use layershellev::{
 };
 use std::{
     borrow::Cow,
-    collections::{HashMap, VecDeque},
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
     mem,
     os::fd::AsFd,
     sync::Arc,
@@ -47,6 +48,17 @@ use std::{
 };
 use window_manager::Window;
 
+thread_local! {
+    /// The windows changed by the message being updated, set by [`redraw_only`]
+    static REDRAW_SCOPE: RefCell<Option<Vec<IcedId>>> = const { RefCell::new(None) };
+}
+
+/// Called while updating a message that only changes some windows, so only those are redrawn after it.
+/// Every window is still rebuilt, only the redraw is skipped. Without it every window is redrawn after a message
+pub fn redraw_only(windows: impl IntoIterator<Item = IcedId>) {
+    REDRAW_SCOPE.with_borrow_mut(|scope| scope.get_or_insert_with(Vec::new).extend(windows));
+}
+
 mod state;
 mod window_manager;
 
@@ -894,17 +906,27 @@ where
         }
 
         if !self.messages.is_empty() {
-            ev.request_refresh_all(RefreshRequest::NextFrame);
             let (caches, application) = self.user_interfaces.extract_all();
 
             // Update application
-            update(
+            let scope = update(
                 application,
                 &mut self.runtime,
                 &mut self.messages,
                 &mut self.waiting_layer_shell_actions,
             );
 
+            match scope {
+                Some(windows) => {
+                    for iced_id in windows {
+                        if let Some(window) = self.window_manager.get(iced_id) {
+                            ev.request_refresh(window.id, RefreshRequest::NextFrame);
+                        }
+                    }
+                }
+                None => ev.request_refresh_all(RefreshRequest::NextFrame),
+            }
+
             for (_, window) in self.window_manager.iter_mut() {
                 window.state.synchronize(application);
             }
@@ -1020,10 +1042,14 @@ pub(crate) fn update<P: IcedProgram, E: Executor>(
     runtime: &mut MultiRuntime<E, P::Message>,
     messages: &mut Vec<P::Message>,
     waiting_layer_shell_actions: &mut Vec<(Option<iced_core::window::Id>, LayershellCustomAction)>,
-) where
+) -> Option<HashSet<IcedId>>
+where
     P::Theme: DefaultStyle,
     P::Message: 'static + TryInto<LayershellCustomActionWithId, Error = P::Message>,
 {
+    // The windows to redraw, None redraws all of them
+    let mut scope = Some(HashSet::new());
+
     for message in messages.drain(..) {
         // NOTE: avoid something like
         // match message {
@@ -1034,11 +1060,18 @@ pub(crate) fn update<P: IcedProgram, E: Executor>(
             Ok(action) => {
                 let LayershellCustomActionWithId(id, action) = action;
                 waiting_layer_shell_actions.push((id, action));
+                scope = None;
                 continue;
             }
             Err(message) => message,
         };
+
+        REDRAW_SCOPE.set(None);
         let task = runtime.enter(|| application.update(message));
+        match (REDRAW_SCOPE.take(), &mut scope) {
+            (Some(windows), Some(scope)) => scope.extend(windows),
+            _ => scope = None,
+        }
 
         if let Some(stream) = iced_runtime::task::into_stream(task) {
             runtime.run(stream);
@@ -1050,6 +1083,8 @@ pub(crate) fn update<P: IcedProgram, E: Executor>(
 
     iced_debug::subscriptions_tracked(recipes.len());
     runtime.track(recipes);
+
+    scope
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -0,0 +1,110 @@
+use futures::{SinkExt, Stream, StreamExt};
+use iced::stream;
+use tracing::warn;
+use zbus::zvariant::Value;
+
+const NAMESPACE: &str = "org.freedesktop.appearance";
+const KEY: &str = "color-scheme";
+
+/// The light or dark preference of the desktop, as defined by the Settings portal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    #[default]
+    NoPreference,
+    Dark,
+    Light
+}
+
+impl From<u32> for ColorScheme {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::Dark,
+            2 => Self::Light,
+            _ => Self::NoPreference
+        }
+    }
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait Settings {
+    fn read_one(&self, namespace: &str, key: &str) -> zbus::Result<zbus::zvariant::OwnedValue>;
+
+    /// Deprecated in favor of ReadOne, and wraps the value in one more variant
+    fn read(&self, namespace: &str, key: &str) -> zbus::Result<zbus::zvariant::OwnedValue>;
+
+    #[zbus(signal)]
+    fn setting_changed(&self, namespace: &str, key: &str, value: Value<'_>) -> zbus::Result<()>;
+}
+
+/// The color scheme in a value of the portal, unwrapping the variants around it
+fn color_scheme(value: &Value) -> Option<ColorScheme> {
+    match value {
+        Value::U32(value) => Some(ColorScheme::from(*value)),
+        Value::Value(inner) => color_scheme(inner),
+        _ => None
+    }
+}
+
+async fn read_color_scheme(proxy: &SettingsProxy<'_>) -> zbus::Result<Option<ColorScheme>> {
+    let value = match proxy.read_one(NAMESPACE, KEY).await {
+        Ok(value) => value,
+        // ReadOne is only implemented by version 2 of the portal
+        Err(_) => proxy.read(NAMESPACE, KEY).await?
+    };
+
+    Ok(color_scheme(&value))
+}
+
+/// Yields the current color scheme, then every change of it
+pub fn color_scheme_subscription() -> impl Stream<Item = ColorScheme> {
+    stream::channel(8, async |mut output| {
+        let connection = match zbus::Connection::session().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to connect to the session bus for the color scheme: {e}");
+                return;
+            }
+        };
+
+        let proxy = match SettingsProxy::new(&connection).await {
+            Ok(proxy) => proxy,
+            Err(e) => {
+                warn!("Failed to create the settings portal proxy: {e}");
+                return;
+            }
+        };
+
+        // Subscribed before reading, so a change in between isn't missed
+        let mut changes = match proxy.receive_setting_changed().await {
+            Ok(changes) => changes,
+            Err(e) => {
+                warn!("Failed to subscribe to settings portal changes: {e}");
+                return;
+            }
+        };
+
+        match read_color_scheme(&proxy).await {
+            Ok(Some(scheme)) => {
+                let _ = output.send(scheme).await;
+            },
+            Ok(None) => warn!("The settings portal returned an invalid color scheme"),
+            Err(e) => warn!("Failed to read the color scheme from the settings portal: {e}")
+        }
+
+        while let Some(signal) = changes.next().await {
+            let Ok(args) = signal.args() else {
+                continue;
+            };
+
+            if *args.namespace() == NAMESPACE && *args.key() == KEY
+                && let Some(scheme) = color_scheme(args.value())
+            {
+                let _ = output.send(scheme).await;
+            }
+        }
+    })
+}
@@ -1,18 +1,26 @@
 use std::{collections::HashMap, sync::OnceLock};
 
+use tracing::warn;
+
 pub static ASSETS: OnceLock<HashMap<&str, HashMap<&str, &str>>> = OnceLock::new();
 
-/// PANICS if ASSETS_WEATHER is not initialized
-pub fn get_svg(module: &str, name: &str) -> &'static str {
-    let error_msg = format!("Failed to get asset {name} from module {module} from memory");
+/// Shown in place of an asset that doesn't exist
+const MISSING: &str = include_str!("assets/svgs/question_mark.svg");
 
-    ASSETS
+/// The svg with this name, or a question mark if it doesn't exist or the assets aren't loaded
+pub fn get_svg(module: &str, name: &str) -> &'static str {
+    let svg = ASSETS
         .get()
-        .expect(error_msg.as_str())
-        .get(module)
-        .expect(error_msg.as_str())
-        .get(name)
-        .expect(error_msg.as_str())
+        .and_then(|assets| assets.get(module))
+        .and_then(|module| module.get(name));
+
+    match svg {
+        Some(svg) => svg,
+        None => {
+            warn!(module, name, "Missing asset");
+            MISSING
+        }
+    }
 }
 
 pub fn load_assets() {
@@ -65,5 +73,7 @@ pub fn load_assets() {
         ("commons", commons)
     ]);
 
-    ASSETS.set(map).expect("Failed to set ASSETS_WEATHER");
-}
\ No newline at end of file
+    if ASSETS.set(map).is_err() {
+        warn!("The assets were already loaded");
+    }
+}
@@ -0,0 +1,512 @@
+//! A reader for the events of iCalendar files (RFC 5545).
+//! Only what's needed to show the events is read: the time, duration, summary, location and recurrence.
+//! Other components, like VTODO and VTIMEZONE, are skipped, time zones come from the IANA database
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use thiserror::Error;
+
+use super::recurrence::Rule;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum IcsError {
+    #[error("line {line}: invalid {property} \"{value}\"")]
+    InvalidValue {
+        line: usize,
+        property: String,
+        value: String
+    },
+    /// Not fatal, the event is kept without its recurrence
+    #[error("line {line}: invalid RRULE \"{value}\": {reason}, only the first occurrence is shown")]
+    InvalidRule {
+        line: usize,
+        value: String,
+        reason: String
+    },
+    #[error("line {line}: the event has no DTSTART")]
+    MissingStart { line: usize },
+}
+
+/// The time zone a date and time is in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    /// Without a zone, the time is the same everywhere, so it's in the local time zone
+    Floating,
+    Utc,
+    Tz(Tz),
+}
+
+impl Zone {
+    /// The local time of a time in this zone. None if it's skipped by a daylight saving change
+    pub fn localize(&self, time: NaiveDateTime) -> Option<DateTime<Local>> {
+        match self {
+            Self::Floating => Local.from_local_datetime(&time).earliest(),
+            Self::Utc => Some(Utc.from_utc_datetime(&time).with_timezone(&Local)),
+            Self::Tz(tz) => tz.from_local_datetime(&time).earliest().map(|time| time.with_timezone(&Local))
+        }
+    }
+
+    /// The time in this zone of a local time
+    pub fn naive(&self, time: DateTime<Local>) -> NaiveDateTime {
+        match self {
+            Self::Floating => time.naive_local(),
+            Self::Utc => time.naive_utc(),
+            Self::Tz(tz) => time.with_timezone(tz).naive_local()
+        }
+    }
+}
+
+/// A DATE or DATE-TIME value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateValue {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime, Zone),
+}
+
+impl DateValue {
+    /// The date and time in the zone of the value, midnight for dates
+    pub fn naive(&self) -> NaiveDateTime {
+        match self {
+            Self::Date(date) => date.and_time(NaiveTime::MIN),
+            Self::DateTime(time, _) => *time
+        }
+    }
+
+    pub fn zone(&self) -> Zone {
+        match self {
+            Self::Date(_) => Zone::Floating,
+            Self::DateTime(_, zone) => *zone
+        }
+    }
+
+    pub fn to_local(&self) -> Option<DateTime<Local>> {
+        self.zone().localize(self.naive())
+    }
+
+    /// Parses `20261017`, `20261017T100000` or `20261017T100000Z`, in the zone of the TZID parameter if there is one
+    fn parse(value: &str, tzid: Option<&str>) -> Option<Self> {
+        let value = value.trim();
+
+        if value.len() == 8 {
+            return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(Self::Date);
+        }
+
+        let (value, utc) = match value.strip_suffix('Z') {
+            Some(value) => (value, true),
+            None => (value, false)
+        };
+        let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+
+        let zone = match (utc, tzid) {
+            (true, _) => Zone::Utc,
+            // Unknown zones, like the Windows names of some exports, are read as local time
+            (false, Some(tzid)) => tzid.trim_start_matches('/').parse().map(Zone::Tz).unwrap_or(Zone::Floating),
+            (false, None) => Zone::Floating
+        };
+
+        Some(Self::DateTime(time, zone))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: DateValue,
+    /// The length of every occurrence
+    pub duration: TimeDelta,
+    pub rule: Option<Rule>,
+    /// The starts of the occurrences removed from the recurrence
+    pub exceptions: Vec<DateValue>,
+    /// Set on a changed occurrence of a recurring event, it replaces the occurrence that started at this time
+    pub recurrence_id: Option<DateValue>,
+}
+
+impl Event {
+    pub fn all_day(&self) -> bool {
+        matches!(self.start, DateValue::Date(_))
+    }
+}
+
+/// A property of a content line, like `DTSTART;TZID=Europe/Paris:20261017T100000`
+#[derive(Debug)]
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl Property<'_> {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.trim_matches('"'))
+    }
+}
+
+/// Splits a content line at the first colon that isn't in a quoted parameter value
+fn parse_property(line: &str) -> Option<Property<'_>> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some(i),
+            _ => {}
+        }
+        None
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(name, value)| (name.trim().to_ascii_uppercase(), value))
+        .collect();
+
+    Some(Property { name, params, value })
+}
+
+/// Joins the lines folded onto the next ones, each with the number of the line it starts on
+fn unfold(source: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, previous))) => previous.push_str(rest),
+            _ => lines.push((i + 1, line.to_string()))
+        }
+    }
+
+    lines
+}
+
+/// Removes the escapes of a TEXT value
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => {}
+        }
+    }
+
+    text
+}
+
+/// Parses a DURATION, like `PT1H30M`, `P1D` or `-P1W`
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value))
+    };
+    let value = value.strip_prefix('P')?;
+
+    let mut duration = TimeDelta::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                let amount: i64 = number.parse().ok()?;
+                number.clear();
+
+                duration += match (unit, in_time) {
+                    ('W', false) => TimeDelta::try_weeks(amount)?,
+                    ('D', false) => TimeDelta::try_days(amount)?,
+                    ('H', true) => TimeDelta::try_hours(amount)?,
+                    ('M', true) => TimeDelta::try_minutes(amount)?,
+                    ('S', true) => TimeDelta::try_seconds(amount)?,
+                    _ => return None
+                };
+            }
+        }
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    Some(if negative { -duration } else { duration })
+}
+
+/// The properties of a VEVENT being read
+#[derive(Default)]
+struct EventBuilder {
+    line: usize,
+    uid: Option<String>,
+    summary: Option<String>,
+    location: Option<String>,
+    start: Option<DateValue>,
+    end: Option<DateValue>,
+    duration: Option<TimeDelta>,
+    rule: Option<Rule>,
+    exceptions: Vec<DateValue>,
+    recurrence_id: Option<DateValue>,
+    cancelled: bool,
+}
+
+impl EventBuilder {
+    fn set(&mut self, line: usize, property: Property) -> Result<(), IcsError> {
+        let invalid = || IcsError::InvalidValue {
+            line,
+            property: property.name.clone(),
+            value: property.value.to_string()
+        };
+        let date = || DateValue::parse(property.value, property.param("TZID")).ok_or_else(invalid);
+
+        match property.name.as_str() {
+            "UID" => self.uid = Some(property.value.trim().to_string()),
+            "SUMMARY" => self.summary = Some(unescape(property.value)),
+            "LOCATION" => self.location = Some(unescape(property.value)).filter(|location| !location.is_empty()),
+            "DTSTART" => self.start = Some(date()?),
+            "DTEND" => self.end = Some(date()?),
+            "DURATION" => self.duration = Some(parse_duration(property.value).ok_or_else(invalid)?),
+            "RECURRENCE-ID" => self.recurrence_id = Some(date()?),
+            "STATUS" => self.cancelled = property.value.trim().eq_ignore_ascii_case("CANCELLED"),
+            "RRULE" => {
+                let rule = property.value.parse().map_err(|reason| IcsError::InvalidRule {
+                    line,
+                    value: property.value.to_string(),
+                    reason
+                })?;
+                self.rule = Some(rule);
+            },
+            // A list of dates, in the zone of the parameter
+            "EXDATE" => {
+                for value in property.value.split(',') {
+                    let exception = DateValue::parse(value, property.param("TZID")).ok_or_else(invalid)?;
+                    self.exceptions.push(exception);
+                }
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn build(self) -> Result<Option<Event>, IcsError> {
+        if self.cancelled {
+            return Ok(None);
+        }
+
+        let start = self.start.ok_or(IcsError::MissingStart { line: self.line })?;
+
+        // Without an end, all day events last the day, and the others are instants
+        let duration = match (self.end, self.duration) {
+            (Some(end), _) if end.zone() == start.zone() => end.naive() - start.naive(),
+            (Some(end), _) => match (end.to_local(), start.to_local()) {
+                (Some(end), Some(start)) => end - start,
+                _ => end.naive() - start.naive()
+            },
+            (None, Some(duration)) => duration,
+            (None, None) if matches!(start, DateValue::Date(_)) => TimeDelta::days(1),
+            (None, None) => TimeDelta::zero()
+        };
+
+        Ok(Some(Event {
+            uid: self.uid,
+            summary: self.summary.unwrap_or_default(),
+            location: self.location,
+            start,
+            duration: duration.max(TimeDelta::zero()),
+            rule: self.rule,
+            exceptions: self.exceptions,
+            recurrence_id: self.recurrence_id,
+        }))
+    }
+}
+
+/// The events of an iCalendar file.
+/// An invalid event is skipped and its error returned, so one broken event doesn't hide the others
+pub fn parse(source: &str) -> (Vec<Event>, Vec<IcsError>) {
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut event: Option<EventBuilder> = None;
+    // The properties of components nested in the event, like VALARM, are not the event's
+    let mut nested = 0;
+    let mut failed = false;
+
+    for (line, text) in unfold(source) {
+        let Some(property) = parse_property(&text) else {
+            continue;
+        };
+        let name = property.name.clone();
+        let value = property.value.trim();
+
+        match (name.as_str(), event.is_some()) {
+            ("BEGIN", false) if value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some(EventBuilder { line, ..Default::default() });
+                failed = false;
+            },
+            ("BEGIN", true) => nested += 1,
+            ("END", true) if nested > 0 => nested -= 1,
+            ("END", true) if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some(builder) = event.take() && !failed {
+                    match builder.build() {
+                        Ok(Some(event)) => events.push(event),
+                        Ok(None) => {},
+                        Err(e) => errors.push(e)
+                    }
+                }
+            },
+            (_, true) if nested == 0 && !failed => {
+                if let Some(builder) = event.as_mut()
+                    && let Err(e) = builder.set(line, property)
+                {
+                    failed = !matches!(e, IcsError::InvalidRule { .. });
+                    errors.push(e);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    (events, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::recurrence::Frequency;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn time(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        date(year, month, day).and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn timed_event() {
+        let source = "\
+BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:standup@example.com\r
+DTSTART;TZID=Europe/Berlin:20261019T093000\r
+DTEND;TZID=Europe/Berlin:20261019T094500\r
+SUMMARY:Standup\\, daily\r
+LOCATION:Room 1\r
+BEGIN:VALARM\r
+TRIGGER:-PT5M\r
+DESCRIPTION:Not the summary\r
+END:VALARM\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        let (events, errors) = parse(source);
+
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Standup, daily");
+        assert_eq!(events[0].location.as_deref(), Some("Room 1"));
+        assert_eq!(events[0].start, DateValue::DateTime(time(2026, 10, 19, 9, 30), Zone::Tz(chrono_tz::Europe::Berlin)));
+        assert_eq!(events[0].duration, TimeDelta::minutes(15));
+    }
+
+    #[test]
+    fn all_day_event() {
+        let source = "BEGIN:VEVENT\nDTSTART;VALUE=DATE:20261224\nSUMMARY:Holiday\nEND:VEVENT\n";
+        let (events, _) = parse(source);
+
+        assert!(events[0].all_day());
+        assert_eq!(events[0].start, DateValue::Date(date(2026, 12, 24)));
+        assert_eq!(events[0].duration, TimeDelta::days(1));
+    }
+
+    #[test]
+    fn folded_lines() {
+        let source = "BEGIN:VEVENT\nDTSTART:20261019T080000Z\nSUMMARY:A very long\n  summary\nEND:VEVENT\n";
+        let (events, _) = parse(source);
+
+        assert_eq!(events[0].summary, "A very long summary");
+        assert_eq!(events[0].start, DateValue::DateTime(time(2026, 10, 19, 8, 0), Zone::Utc));
+    }
+
+    #[test]
+    fn recurrence_and_exceptions() {
+        let source = "\
+BEGIN:VEVENT
+DTSTART:20261019T100000
+DURATION:PT1H30M
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10
+EXDATE:20261021T100000,20261026T100000
+END:VEVENT
+";
+        let (events, errors) = parse(source);
+
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(events[0].duration, TimeDelta::minutes(90));
+        assert_eq!(events[0].rule.as_ref().map(|rule| rule.frequency), Some(Frequency::Weekly));
+        assert_eq!(events[0].exceptions.len(), 2);
+    }
+
+    #[test]
+    fn invalid_event_is_skipped() {
+        let source = "\
+BEGIN:VEVENT
+DTSTART:tomorrow
+SUMMARY:Broken
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20261019
+SUMMARY:Fine
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:No start
+END:VEVENT
+";
+        let (events, errors) = parse(source);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Fine");
+        assert_eq!(errors, vec![
+            IcsError::InvalidValue { line: 2, property: String::from("DTSTART"), value: String::from("tomorrow") },
+            IcsError::MissingStart { line: 9 },
+        ]);
+    }
+
+    #[test]
+    fn unsupported_rule_keeps_the_event() {
+        let source = "BEGIN:VEVENT\nDTSTART:20261019T100000\nRRULE:FREQ=YEARLY;BYWEEKNO=20\nSUMMARY:Review\nEND:VEVENT\n";
+        let (events, errors) = parse(source);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Review");
+        assert!(events[0].rule.is_none());
+        assert!(matches!(errors[..], [IcsError::InvalidRule { line: 3, .. }]), "{errors:?}");
+    }
+
+    #[test]
+    fn cancelled_event_is_skipped() {
+        let source = "BEGIN:VEVENT\nDTSTART:20261019\nSTATUS:CANCELLED\nEND:VEVENT\n";
+        assert!(parse(source).0.is_empty());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("PT1H30M"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("P1W"), Some(TimeDelta::weeks(1)));
+        assert_eq!(parse_duration("P1DT12H"), Some(TimeDelta::hours(36)));
+        assert_eq!(parse_duration("-PT15M"), Some(TimeDelta::minutes(-15)));
+        assert_eq!(parse_duration("PT15"), None);
+        assert_eq!(parse_duration("1H"), None);
+    }
+}
@@ -0,0 +1,205 @@
+//! Events of local calendars, read from directories of iCalendar files like the ones synced by vdirsyncer
+
+use std::{io, path::{Path, PathBuf}, sync::Arc};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use thiserror::Error;
+
+/// The parser of `.ics` files
+mod ics;
+pub use ics::{Event, IcsError};
+
+/// The expansion of recurring events
+mod recurrence;
+
+#[derive(Debug, Error, Clone)]
+pub enum CalendarError {
+    #[error("Failed to read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: Arc<io::Error>
+    },
+    #[error("{}: {source}", path.display())]
+    Invalid {
+        path: PathBuf,
+        source: IcsError
+    }
+}
+
+/// An occurrence of an event, in local time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrence {
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: DateTime<Local>,
+    /// Exclusive, midnight after the last day for all day events
+    pub end: DateTime<Local>,
+    pub all_day: bool,
+}
+
+/// Reads the events of the `.ics` files in the directories and their subdirectories. A path can also be a single file
+pub fn load(paths: &[PathBuf]) -> (Vec<Event>, Vec<CalendarError>) {
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+
+    for path in paths {
+        read_path(path, &mut events, &mut errors);
+    }
+
+    (events, errors)
+}
+
+fn read_path(path: &Path, events: &mut Vec<Event>, errors: &mut Vec<CalendarError>) {
+    let io_error = |e: io::Error| CalendarError::Io { path: path.to_path_buf(), source: Arc::new(e) };
+
+    if path.is_dir() {
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                errors.push(io_error(e));
+                return;
+            }
+        };
+
+        // Sorted, so the events are in the same order on every load
+        let mut paths: Vec<PathBuf> = entries.filter_map(Result::ok).map(|entry| entry.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            // vdirsyncer keeps its metadata in hidden files
+            let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            let ics = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("ics"));
+
+            if !hidden && (ics || path.is_dir()) {
+                read_path(&path, events, errors);
+            }
+        }
+        return;
+    }
+
+    match std::fs::read_to_string(path) {
+        Ok(source) => {
+            let (file_events, file_errors) = ics::parse(&source);
+            events.extend(file_events);
+            errors.extend(file_errors.into_iter().map(|source| CalendarError::Invalid { path: path.to_path_buf(), source }));
+        },
+        Err(e) => errors.push(io_error(e))
+    }
+}
+
+/// The occurrences of the events that overlap `from..to`, sorted by their start
+pub fn occurrences(events: &[Event], from: DateTime<Local>, to: DateTime<Local>) -> Vec<Occurrence> {
+    // Changed occurrences of recurring events replace the ones of the rule, they share the uid of the event
+    let overrides: Vec<(&str, DateTime<Local>)> = events
+        .iter()
+        .filter_map(|event| Some((event.uid.as_deref()?, event.recurrence_id?.to_local()?)))
+        .collect();
+
+    let mut occurrences: Vec<Occurrence> = events
+        .iter()
+        .flat_map(|event| event_occurrences(event, from, to, &overrides))
+        .collect();
+
+    occurrences.sort_by_key(|occurrence| (occurrence.start, !occurrence.all_day));
+    occurrences
+}
+
+fn event_occurrences(event: &Event, from: DateTime<Local>, to: DateTime<Local>, overrides: &[(&str, DateTime<Local>)]) -> Vec<Occurrence> {
+    let zone = event.start.zone();
+    let start = event.start.naive();
+
+    let starts: Vec<NaiveDateTime> = match &event.rule {
+        // An occurrence that started before `from` can still be going on
+        Some(rule) => rule.occurrences(start, zone.naive(from - event.duration), zone.naive(to)),
+        None => vec![start]
+    };
+
+    let excluded = |time: DateTime<Local>| {
+        event.exceptions.iter().any(|exception| exception.to_local() == Some(time))
+            || (event.rule.is_some() && overrides.iter().any(|(uid, start)| event.uid.as_deref() == Some(uid) && *start == time))
+    };
+
+    starts
+        .into_iter()
+        .filter_map(|naive| {
+            let start = zone.localize(naive)?;
+            // The end is localized on its own, so all day events end at midnight across daylight saving changes
+            let end = zone.localize(naive + event.duration).unwrap_or(start + event.duration);
+
+            let overlaps = start < to && (end > from || (start == end && start >= from));
+            (overlaps && !excluded(start)).then(|| Occurrence {
+                summary: event.summary.clone(),
+                location: event.location.clone(),
+                start,
+                end,
+                all_day: event.all_day()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn local(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Local> {
+        let time = NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, 0, 0).unwrap();
+        Local.from_local_datetime(&time).unwrap()
+    }
+
+    fn summaries(occurrences: &[Occurrence]) -> Vec<(&str, DateTime<Local>)> {
+        occurrences.iter().map(|occurrence| (occurrence.summary.as_str(), occurrence.start)).collect()
+    }
+
+    #[test]
+    fn recurring_event_with_exception_and_override() {
+        let source = "\
+BEGIN:VEVENT
+UID:standup
+DTSTART:20261019T090000
+DURATION:PT15M
+RRULE:FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR
+EXDATE:20261021T090000
+SUMMARY:Standup
+END:VEVENT
+BEGIN:VEVENT
+UID:standup
+RECURRENCE-ID:20261022T090000
+DTSTART:20261022T113000
+DURATION:PT15M
+SUMMARY:Late standup
+END:VEVENT
+";
+        let (events, _) = ics::parse(source);
+        let occurrences = occurrences(&events, local(2026, 10, 20, 0), local(2026, 10, 24, 0));
+
+        assert_eq!(summaries(&occurrences), vec![
+            ("Standup", local(2026, 10, 20, 9)),
+            ("Late standup", local(2026, 10, 22, 11) + chrono::TimeDelta::minutes(30)),
+            ("Standup", local(2026, 10, 23, 9)),
+        ]);
+    }
+
+    #[test]
+    fn ongoing_events_overlap() {
+        let source = "\
+BEGIN:VEVENT
+DTSTART;VALUE=DATE:20261019
+DTEND;VALUE=DATE:20261022
+SUMMARY:Conference
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20261020T080000
+DURATION:PT1H
+SUMMARY:Over
+END:VEVENT
+";
+        let (events, _) = ics::parse(source);
+        let occurrences = occurrences(&events, local(2026, 10, 20, 12), local(2026, 10, 21, 0));
+
+        assert_eq!(summaries(&occurrences), vec![("Conference", local(2026, 10, 19, 0))]);
+        assert!(occurrences[0].all_day);
+        assert_eq!(occurrences[0].end, local(2026, 10, 22, 0));
+    }
+}
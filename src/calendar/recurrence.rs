@@ -0,0 +1,412 @@
+//! Recurrence rules (RRULE) of events, expanded into the starts of their occurrences.
+//! Daily, weekly, monthly and yearly rules are supported, with the BYDAY, BYMONTHDAY, BYMONTH and BYSETPOS parts
+
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+
+/// Stops the expansion of rules that never produce an occurrence, like the 31st of february
+const MAX_PERIODS: u32 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub frequency: Frequency,
+    /// Every how many days, weeks, months or years
+    pub interval: u32,
+    pub count: Option<u32>,
+    /// The last possible start, in the zone of the event
+    pub until: Option<NaiveDateTime>,
+    /// Weekdays, with the n-th of the month or year for monthly and yearly rules, negative from the end
+    pub by_day: Vec<(Option<i32>, Weekday)>,
+    /// Days of the month, negative from the end
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+    /// The n-th occurrences of every period, negative from the end
+    pub by_set_pos: Vec<i32>,
+}
+
+/// Parses a list of the part, like `MO,WE,FR`
+fn list<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(|item| parse(item.trim()).ok_or_else(|| format!("invalid item \"{item}\"")))
+        .collect()
+}
+
+/// Parses a weekday of BYDAY, like `MO`, `1MO` or `-1FR`
+fn parse_day(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let split = value.len().checked_sub(2)?;
+    let (ordinal, day) = value.split_at_checked(split)?;
+
+    let day = match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None
+    };
+    let ordinal = match ordinal {
+        "" => None,
+        ordinal => Some(ordinal.trim_start_matches('+').parse().ok().filter(|n: &i32| *n != 0)?)
+    };
+
+    Some((ordinal, day))
+}
+
+/// Parses UNTIL, a date includes the whole day. A UTC time is compared as is, a few hours off at worst
+fn parse_until(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim_end_matches('Z');
+
+    match value.len() {
+        8 => NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(|date| date.and_time(NaiveTime::MIN) + Days::new(1) - TimeDelta::seconds(1)),
+        _ => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut frequency = None;
+        let mut rule = Rule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            by_set_pos: Vec::new(),
+        };
+
+        for part in value.trim().split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part.split_once('=').ok_or_else(|| format!("invalid part \"{part}\""))?;
+            let invalid = || format!("invalid {name} \"{value}\"");
+
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => frequency = Some(match value {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    other => return Err(format!("FREQ={other} is not supported"))
+                }),
+                "INTERVAL" => rule.interval = value.parse::<u32>().ok().filter(|interval| *interval > 0).ok_or_else(invalid)?,
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid())?),
+                "UNTIL" => rule.until = Some(parse_until(value).ok_or_else(invalid)?),
+                "BYDAY" => rule.by_day = list(value, parse_day)?,
+                "BYMONTHDAY" => rule.by_month_day = list(value, |day| day.parse::<i32>().ok().filter(|day| (1..=31).contains(&day.abs())))?,
+                "BYMONTH" => rule.by_month = list(value, |month| month.parse::<u32>().ok().filter(|month| (1..=12).contains(month)))?,
+                "BYSETPOS" => rule.by_set_pos = list(value, |pos| pos.parse::<i32>().ok().filter(|pos| (1..=366).contains(&pos.abs())))?,
+                // Only changes weekly rules with an interval and several days, rarely enough to be ignored
+                "WKST" => {},
+                other => return Err(format!("{other} is not supported"))
+            }
+        }
+
+        rule.frequency = frequency.ok_or("FREQ is missing")?;
+        Ok(rule)
+    }
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// The n-th of the items, or the n-th from the end if negative
+fn nth<T: Copy>(items: &[T], n: i32) -> Option<T> {
+    let index = if n > 0 { n as usize - 1 } else { items.len().checked_sub(n.unsigned_abs() as usize)? };
+    items.get(index).copied()
+}
+
+/// The n-th day of the month or the n-th from the end if negative, None if the month is too short
+fn month_day(first: NaiveDate, day: i32) -> Option<NaiveDate> {
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?.day() as i32;
+    let day = if day < 0 { last + 1 + day } else { day };
+
+    (1..=last).contains(&day).then(|| first.with_day(day as u32)).flatten()
+}
+
+impl Rule {
+    fn matches_month(&self, date: NaiveDate) -> bool {
+        self.by_month.is_empty() || self.by_month.contains(&date.month())
+    }
+
+    /// The days of BYDAY in `days`, a month or a whole year the ordinals count in
+    fn weekdays_in(&self, days: &[NaiveDate]) -> Vec<NaiveDate> {
+        self.by_day
+            .iter()
+            .flat_map(|(ordinal, weekday)| {
+                let matching: Vec<NaiveDate> = days.iter().copied().filter(|day| day.weekday() == *weekday).collect();
+                match ordinal {
+                    None => matching,
+                    Some(n) => nth(&matching, *n).into_iter().collect()
+                }
+            })
+            // Both parts limit the days
+            .filter(|day| self.by_month_day.is_empty() || self.by_month_day.iter().any(|n| month_day(first_of_month(*day), *n) == Some(*day)))
+            .collect()
+    }
+
+    /// The days of a month the rule occurs on, `default_day` is the day of the start of the event
+    fn month_days(&self, first: NaiveDate, default_day: u32) -> Vec<NaiveDate> {
+        let mut days: Vec<NaiveDate> = if self.by_day.is_empty() {
+            if self.by_month_day.is_empty() {
+                month_day(first, default_day as i32).into_iter().collect()
+            } else {
+                self.by_month_day.iter().filter_map(|day| month_day(first, *day)).collect()
+            }
+        } else {
+            let month: Vec<NaiveDate> = first.iter_days().take_while(|day| day.month() == first.month()).collect();
+            self.weekdays_in(&month)
+        };
+
+        days.sort();
+        days.dedup();
+        days
+    }
+
+    /// The days of a year the rule occurs on, `start` is the start of the event
+    fn year_days(&self, january: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let months = |months: &[u32]| -> Vec<NaiveDate> {
+            months
+                .iter()
+                .filter_map(|month| NaiveDate::from_ymd_opt(january.year(), *month, 1))
+                .flat_map(|first| self.month_days(first, start.day()))
+                .collect()
+        };
+
+        let mut days = if !self.by_month.is_empty() {
+            months(&self.by_month)
+        } else if !self.by_day.is_empty() {
+            // Without months, the weekdays are of the whole year, and so are their ordinals
+            let year: Vec<NaiveDate> = january.iter_days().take_while(|day| day.year() == january.year()).collect();
+            self.weekdays_in(&year)
+        } else if !self.by_month_day.is_empty() {
+            months(&(1..=12).collect::<Vec<u32>>())
+        } else {
+            months(&[start.month()])
+        };
+
+        days.sort();
+        days.dedup();
+        days
+    }
+
+    /// The first day of the n-th period of the rule, and the days in it the rule occurs on
+    fn period(&self, start: NaiveDate, n: u32) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let (first, days) = self.period_days(start, n)?;
+        if self.by_set_pos.is_empty() {
+            return Some((first, days));
+        }
+
+        let mut picked: Vec<NaiveDate> = self.by_set_pos.iter().filter_map(|pos| nth(&days, *pos)).collect();
+        picked.sort();
+        picked.dedup();
+        Some((first, picked))
+    }
+
+    /// The first day of the n-th period of the rule, and every day in it the rule occurs on before BYSETPOS
+    fn period_days(&self, start: NaiveDate, n: u32) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let steps = n.checked_mul(self.interval)?;
+
+        match self.frequency {
+            Frequency::Daily => {
+                let day = start.checked_add_days(Days::new(steps.into()))?;
+                let matches = self.matches_month(day)
+                    && (self.by_day.is_empty() || self.by_day.iter().any(|(_, weekday)| *weekday == day.weekday()))
+                    && (self.by_month_day.is_empty() || self.by_month_day.iter().any(|n| month_day(first_of_month(day), *n) == Some(day)));
+
+                Some((day, if matches { vec![day] } else { Vec::new() }))
+            },
+            Frequency::Weekly => {
+                let monday = start
+                    .checked_sub_days(Days::new(start.weekday().num_days_from_monday().into()))?
+                    .checked_add_days(Days::new(u64::from(steps) * 7))?;
+
+                let weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|(_, weekday)| *weekday).collect()
+                };
+
+                let mut days: Vec<NaiveDate> = weekdays
+                    .iter()
+                    .filter_map(|weekday| monday.checked_add_days(Days::new(weekday.num_days_from_monday().into())))
+                    .filter(|day| self.matches_month(*day))
+                    .collect();
+                days.sort();
+                days.dedup();
+
+                Some((monday, days))
+            },
+            Frequency::Monthly => {
+                let first = first_of_month(start).checked_add_months(Months::new(steps))?;
+                let days = if self.matches_month(first) { self.month_days(first, start.day()) } else { Vec::new() };
+
+                Some((first, days))
+            },
+            Frequency::Yearly => {
+                let year = start.year().checked_add(steps.try_into().ok()?)?;
+                let january = NaiveDate::from_ymd_opt(year, 1, 1)?;
+
+                Some((january, self.year_days(january, start)))
+            }
+        }
+    }
+
+    /// The starts of the occurrences in `from..to`, in the zone of the event.
+    /// The start of the event is always the first occurrence, even if the rule doesn't match it
+    pub fn occurrences(&self, start: NaiveDateTime, from: NaiveDateTime, to: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let mut occurrences = Vec::new();
+        let mut count = 1;
+
+        let in_range = |time: NaiveDateTime| time >= from && time < to;
+        if in_range(start) {
+            occurrences.push(start);
+        }
+
+        for n in 0..MAX_PERIODS {
+            let Some((period_start, days)) = self.period(start.date(), n) else {
+                break;
+            };
+            if period_start > to.date() {
+                break;
+            }
+
+            for time in days.into_iter().map(|day| day.and_time(start.time())) {
+                if time <= start {
+                    continue;
+                }
+                if time >= to || self.until.is_some_and(|until| time > until) {
+                    return occurrences;
+                }
+
+                count += 1;
+                if self.count.is_some_and(|max| count > max) {
+                    return occurrences;
+                }
+
+                if in_range(time) {
+                    occurrences.push(time);
+                }
+            }
+        }
+
+        occurrences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        date(year, month, day).and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn days(rule: &str, start: NaiveDateTime, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let rule: Rule = rule.parse().unwrap();
+        rule.occurrences(start, from.and_time(NaiveTime::MIN), to.and_time(NaiveTime::MIN))
+            .into_iter()
+            .map(|time| time.date())
+            .collect()
+    }
+
+    #[test]
+    fn daily_with_count() {
+        let occurrences = days("FREQ=DAILY;COUNT=3", at(2026, 10, 30, 9), date(2026, 10, 1), date(2026, 12, 1));
+        assert_eq!(occurrences, vec![date(2026, 10, 30), date(2026, 10, 31), date(2026, 11, 1)]);
+    }
+
+    #[test]
+    fn weekly_on_several_days() {
+        // The 19th is a monday
+        let occurrences = days("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR", at(2026, 10, 19, 9), date(2026, 10, 1), date(2026, 11, 7));
+        assert_eq!(occurrences, vec![date(2026, 10, 19), date(2026, 10, 23), date(2026, 11, 2), date(2026, 11, 6)]);
+    }
+
+    #[test]
+    fn count_includes_occurrences_before_the_range() {
+        let occurrences = days("FREQ=WEEKLY;COUNT=3", at(2026, 10, 5, 9), date(2026, 10, 15), date(2026, 12, 1));
+        assert_eq!(occurrences, vec![date(2026, 10, 19)]);
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        let occurrences = days("FREQ=DAILY;UNTIL=20261021", at(2026, 10, 19, 9), date(2026, 10, 1), date(2026, 12, 1));
+        assert_eq!(occurrences, vec![date(2026, 10, 19), date(2026, 10, 20), date(2026, 10, 21)]);
+    }
+
+    #[test]
+    fn monthly_last_friday() {
+        let occurrences = days("FREQ=MONTHLY;BYDAY=-1FR", at(2026, 10, 30, 9), date(2026, 10, 1), date(2027, 1, 1));
+        assert_eq!(occurrences, vec![date(2026, 10, 30), date(2026, 11, 27), date(2026, 12, 25)]);
+    }
+
+    #[test]
+    fn monthly_skips_short_months() {
+        let occurrences = days("FREQ=MONTHLY", at(2027, 1, 31, 9), date(2027, 1, 1), date(2027, 6, 1));
+        assert_eq!(occurrences, vec![date(2027, 1, 31), date(2027, 3, 31), date(2027, 5, 31)]);
+    }
+
+    #[test]
+    fn yearly_birthday() {
+        let occurrences = days("FREQ=YEARLY", at(1990, 3, 14, 0), date(2026, 1, 1), date(2028, 1, 1));
+        assert_eq!(occurrences, vec![date(2026, 3, 14), date(2027, 3, 14)]);
+    }
+
+    #[test]
+    fn yearly_nth_weekday_of_month() {
+        // Thanksgiving, the fourth thursday of november
+        let occurrences = days("FREQ=YEARLY;BYMONTH=11;BYDAY=4TH", at(2026, 11, 26, 0), date(2026, 1, 1), date(2028, 1, 1));
+        assert_eq!(occurrences, vec![date(2026, 11, 26), date(2027, 11, 25)]);
+    }
+
+    #[test]
+    fn yearly_weekday_of_the_year() {
+        // Every monday of the year without BYMONTH, not only the ones of the month of the start
+        let mondays = days("FREQ=YEARLY;BYDAY=MO", at(2026, 1, 5, 9), date(2026, 1, 1), date(2027, 1, 1));
+        assert_eq!(mondays.len(), 52);
+
+        // The 20th monday of the year
+        let occurrences = days("FREQ=YEARLY;BYDAY=20MO", at(2026, 5, 18, 9), date(2026, 1, 1), date(2028, 1, 1));
+        assert_eq!(occurrences, vec![date(2026, 5, 18), date(2027, 5, 17)]);
+    }
+
+    #[test]
+    fn yearly_month_day_of_every_month() {
+        let occurrences = days("FREQ=YEARLY;BYMONTHDAY=1", at(2026, 10, 1, 9), date(2026, 10, 1), date(2027, 1, 1));
+        assert_eq!(occurrences, vec![date(2026, 10, 1), date(2026, 11, 1), date(2026, 12, 1)]);
+    }
+
+    #[test]
+    fn monthly_last_workday() {
+        let occurrences = days("FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1", at(2026, 10, 30, 9), date(2026, 10, 1), date(2027, 1, 1));
+        assert_eq!(occurrences, vec![date(2026, 10, 30), date(2026, 11, 30), date(2026, 12, 31)]);
+    }
+
+    #[test]
+    fn unsupported_rules_rejected() {
+        assert!("FREQ=HOURLY".parse::<Rule>().is_err());
+        assert!("FREQ=MONTHLY;BYSETPOS=0".parse::<Rule>().is_err());
+        assert!("FREQ=YEARLY;BYWEEKNO=20".parse::<Rule>().is_err());
+        assert!("INTERVAL=2".parse::<Rule>().is_err());
+        assert!("FREQ=WEEKLY;BYDAY=XX".parse::<Rule>().is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! The command line of the `rusty_bar` binary
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+pub const USAGE: &str = "\
+Usage:
+  rusty_bar [--config <path>]               Run the bar
+  rusty_bar msg <command>                   Send a command to the running bar
+  rusty_bar check-config [--config <path>]  Validate the config without starting the bar
+  rusty_bar print-default-config            Print the default config, with every option documented
+  rusty_bar --help                          Print this message";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CliError {
+    #[error("{0} expects a value")]
+    MissingValue(&'static str),
+    #[error("msg expects a command")]
+    MissingCommand,
+    #[error("unexpected argument \"{0}\"")]
+    Unexpected(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Cli {
+    /// Runs the bar, with the config at the path or the default one
+    Run { config: Option<PathBuf> },
+    /// Sends a command to the socket of the running bar
+    Msg { command: String },
+    CheckConfig { config: Option<PathBuf> },
+    PrintDefaultConfig,
+    Help,
+}
+
+impl Cli {
+    /// Parses the arguments, without the name of the binary
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut args = args.into_iter();
+        let mut config = None;
+        let mut check = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" | "-c" => {
+                    config = Some(PathBuf::from(args.next().ok_or(CliError::MissingValue("--config"))?));
+                },
+                "--help" | "-h" | "help" => return Ok(Self::Help),
+                // Neither reads the config, so a path would be silently ignored
+                "msg" | "print-default-config" if config.is_some() => return Err(CliError::Unexpected(String::from("--config"))),
+                // Everything after msg is the command, so commands can't be confused with options
+                "msg" => {
+                    let command: Vec<String> = args.collect();
+                    if command.is_empty() {
+                        return Err(CliError::MissingCommand);
+                    }
+                    return Ok(Self::Msg { command: command.join(" ") });
+                },
+                "check-config" if !check => check = true,
+                "print-default-config" => {
+                    return match args.next() {
+                        Some(arg) => Err(CliError::Unexpected(arg)),
+                        None => Ok(Self::PrintDefaultConfig)
+                    };
+                },
+                _ => return Err(CliError::Unexpected(arg))
+            }
+        }
+
+        if check {
+            Ok(Self::CheckConfig { config })
+        } else {
+            Ok(Self::Run { config })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, CliError> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_runs_the_bar() {
+        assert_eq!(parse(&[]), Ok(Cli::Run { config: None }));
+    }
+
+    #[test]
+    fn config_path() {
+        assert_eq!(parse(&["--config", "bar.toml"]), Ok(Cli::Run { config: Some(PathBuf::from("bar.toml")) }));
+        assert_eq!(parse(&["check-config", "-c", "bar.toml"]), Ok(Cli::CheckConfig { config: Some(PathBuf::from("bar.toml")) }));
+        assert_eq!(parse(&["--config"]), Err(CliError::MissingValue("--config")));
+        assert_eq!(parse(&["--config", "bar.toml", "msg", "reload"]), Err(CliError::Unexpected(String::from("--config"))));
+        assert_eq!(parse(&["-c", "bar.toml", "print-default-config"]), Err(CliError::Unexpected(String::from("--config"))));
+        assert_eq!(parse(&["print-default-config", "--config", "bar.toml"]), Err(CliError::Unexpected(String::from("--config"))));
+    }
+
+    #[test]
+    fn msg_takes_the_rest() {
+        assert_eq!(parse(&["msg", "weather", "refresh"]), Ok(Cli::Msg { command: String::from("weather refresh") }));
+        assert_eq!(parse(&["msg"]), Err(CliError::MissingCommand));
+    }
+
+    #[test]
+    fn unknown_argument_rejected() {
+        assert_eq!(parse(&["--verbose"]), Err(CliError::Unexpected(String::from("--verbose"))));
+        assert_eq!(parse(&["print-default-config", "now"]), Err(CliError::Unexpected(String::from("now"))));
+    }
+}
@@ -0,0 +1,1007 @@
+//! The user configuration of rusty bar, read from `$XDG_CONFIG_HOME/rusty-bar/config.toml`
+//! Every field is optional, anything missing falls back to the values in `default_config.toml`
+
+use std::{collections::{BTreeMap, HashMap}, io, ops::Range, path::{Path, PathBuf}, sync::Arc};
+
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use iced::Theme;
+use iced_layershell::reexport::{Anchor, Layer};
+use serde::{Deserialize, Deserializer, de::Error as _};
+use thiserror::Error;
+
+use crate::{appearance::ColorScheme, modules::Module, weather::prelude::{Coordinates, Units}};
+
+/// Hot reloading of the config file
+mod watcher;
+pub use watcher::watch;
+
+/// Built in and custom themes
+mod theme;
+use theme::{deserialize_optional_theme, deserialize_theme};
+
+/// The name of the bar configured by the top level `[bar]` and `[layout]` sections
+pub const MAIN_BAR: &str = "main";
+
+/// The default config, with every option documented
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+#[derive(Debug, Error, Clone)]
+pub enum ConfigError {
+    #[error("Failed to read config file {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: Arc<io::Error>
+    },
+    #[error("{}:{line}:{column}: {message}", path.display())]
+    Invalid {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// A built in theme, or a custom palette
+    #[serde(deserialize_with = "deserialize_theme")]
+    pub theme: Theme,
+    /// Used instead of `theme` while the desktop prefers a light color scheme
+    #[serde(deserialize_with = "deserialize_optional_theme")]
+    pub light_theme: Option<Theme>,
+    /// Used instead of `theme` while the desktop prefers a dark color scheme
+    #[serde(deserialize_with = "deserialize_optional_theme")]
+    pub dark_theme: Option<Theme>,
+    /// The corner radius of the bar and its widgets
+    pub radius: u16,
+    /// Space between widgets
+    pub spacing: u32,
+    /// Horizontal padding inside widgets
+    pub hpadding: u32,
+
+    pub clock: ClockConfig,
+    pub calendar: CalendarConfig,
+    pub timer: TimerConfig,
+    pub notifications: NotificationsConfig,
+    pub weather: WeatherConfig,
+    pub bar: BarConfig,
+    pub layout: LayoutConfig,
+    pub log: LogConfig,
+    /// More bars besides the main one, keyed by their name
+    #[serde(deserialize_with = "deserialize_bars")]
+    pub bars: BTreeMap<String, BarInstanceConfig>,
+    /// Per output overrides, keyed by the output's name (like "eDP-1") or description
+    pub outputs: HashMap<String, OutputConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: Theme::CatppuccinMocha,
+            light_theme: None,
+            dark_theme: None,
+            radius: 10,
+            spacing: 4,
+            hpadding: 4,
+            clock: ClockConfig::default(),
+            calendar: CalendarConfig::default(),
+            timer: TimerConfig::default(),
+            notifications: NotificationsConfig::default(),
+            weather: WeatherConfig::default(),
+            bar: BarConfig::default(),
+            layout: LayoutConfig::default(),
+            log: LogConfig::default(),
+            bars: BTreeMap::new(),
+            outputs: HashMap::new()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockConfig {
+    /// A chrono strftime format string
+    #[serde(deserialize_with = "deserialize_time_format")]
+    pub format: String,
+    /// The format used on vertical bars, where a line has little room
+    #[serde(deserialize_with = "deserialize_time_format")]
+    pub vertical_format: String,
+    pub width: u32,
+    /// Other time zones, scrolling on the clock goes through them
+    pub zones: Vec<ClockZone>,
+    /// Shows the other zones next to the clock while the pointer is on it
+    pub zones_on_hover: bool,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            format: String::from("%H:%M:%S"),
+            vertical_format: String::from("%H\n%M"),
+            width: 140,
+            zones: Vec::new(),
+            zones_on_hover: true
+        }
+    }
+}
+
+/// An extra time zone of the clock
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClockZone {
+    /// The label of the zone, like "NYC"
+    pub name: String,
+    /// An IANA time zone, like "America/New_York"
+    #[serde(deserialize_with = "deserialize_time_zone")]
+    pub timezone: Tz,
+    /// Defaults to the format of the clock
+    #[serde(default, deserialize_with = "deserialize_optional_time_format")]
+    pub format: Option<String>,
+}
+
+/// Local calendars, their events are shown in the calendar popup of the clock
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// Directories of `.ics` files, like the ones synced by vdirsyncer, or single files
+    #[serde(deserialize_with = "deserialize_paths")]
+    pub paths: Vec<PathBuf>,
+    /// Shows the next event next to the clock, like "Standup in 12 min"
+    pub next_event: bool,
+    /// Minutes before its start the next event is shown
+    pub next_event_within: u32,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            next_event: false,
+            next_event_within: 60
+        }
+    }
+}
+
+/// The durations of the timer module, in minutes
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimerConfig {
+    /// The countdown, scrolling on the stopped timer changes it
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub countdown: u32,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub work: u32,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub short_break: u32,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub long_break: u32,
+    /// The number of work phases before a long break
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub long_break_after: u32,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        Self {
+            countdown: 10,
+            work: 25,
+            short_break: 5,
+            long_break: 15,
+            long_break_after: 4
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Serves org.freedesktop.Notifications, so desktop apps notify through the bar
+    pub server: bool,
+    /// Seconds a notification of an app is shown, unless the app asks for another timeout
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub timeout: u32,
+    /// The most toasts shown at once, the newest ones are shown
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub max_toasts: u32,
+    /// The most toasts a single app, or the bar itself, shows in a minute
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub toasts_per_minute: u32,
+    /// The most notifications listed, the oldest read ones are dropped first
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub max_entries: u32,
+    /// Days notifications are kept in the history, 0 turns the history off
+    pub history_days: u32,
+    pub dnd: DndConfig,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            server: false,
+            timeout: 5,
+            max_toasts: 5,
+            toasts_per_minute: 5,
+            max_entries: 100,
+            history_days: 7,
+            dnd: DndConfig::default()
+        }
+    }
+}
+
+/// Do not disturb, no toasts are shown while it's on but notifications are still listed
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DndConfig {
+    /// The times of the day it's turned on
+    #[serde(deserialize_with = "deserialize_schedules")]
+    pub schedules: Vec<Schedule>,
+    /// Turned on while a media player is fullscreen
+    pub fullscreen: bool,
+    /// The critical notifications of apps are still shown
+    pub allow_critical: bool,
+    /// The errors of the bar are still shown
+    pub allow_errors: bool,
+}
+
+impl Default for DndConfig {
+    fn default() -> Self {
+        Self {
+            schedules: Vec::new(),
+            fullscreen: true,
+            allow_critical: true,
+            allow_errors: false
+        }
+    }
+}
+
+/// A time range of the day, like 22:00-07:00. It goes past midnight if it ends before it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Schedule {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeatherConfig {
+    /// None if the location should be looked up from the ip address
+    #[serde(deserialize_with = "deserialize_location")]
+    pub location: Option<Coordinates>,
+    pub units: Units,
+    /// The number of hours parsed for the hourly forecast
+    pub hours: u8,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            location: None,
+            units: Units::default(),
+            hours: 24
+        }
+    }
+}
+
+/// The screen edge the bar is attached to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    #[default]
+    Top,
+    Bottom,
+    Left,
+    Right
+}
+
+impl Position {
+    /// Left and right bars lay their modules out in a column
+    pub fn is_vertical(&self) -> bool {
+        matches!(self, Position::Left | Position::Right)
+    }
+
+    /// The edge itself and both edges next to it, so the bar spans the whole side
+    /// and the compositor reserves the exclusive zone on that edge
+    pub fn anchor(&self) -> Anchor {
+        match self {
+            Position::Top => Anchor::Top | Anchor::Left | Anchor::Right,
+            Position::Bottom => Anchor::Bottom | Anchor::Left | Anchor::Right,
+            Position::Left => Anchor::Left | Anchor::Top | Anchor::Bottom,
+            Position::Right => Anchor::Right | Anchor::Top | Anchor::Bottom,
+        }
+    }
+
+    /// The corner toasts open in, the right one next to the bar
+    pub fn toast_anchor(&self) -> Anchor {
+        match self {
+            Position::Bottom => Anchor::Bottom | Anchor::Right,
+            Position::Top | Position::Left | Position::Right => Anchor::Top | Anchor::Right,
+        }
+    }
+
+    /// The corner popups open in, next to the bar
+    pub fn popup_anchor(&self) -> Anchor {
+        match self {
+            Position::Top | Position::Left => Anchor::Top | Anchor::Left,
+            Position::Bottom => Anchor::Bottom | Anchor::Left,
+            Position::Right => Anchor::Top | Anchor::Right,
+        }
+    }
+
+    /// top, right, bottom, left margin of popups, a small gap from the bar and a larger one along it
+    pub fn popup_margin(&self) -> (i32, i32, i32, i32) {
+        match self {
+            Position::Top => (10, 0, 0, 30),
+            Position::Bottom => (0, 0, 10, 30),
+            Position::Left => (30, 0, 0, 10),
+            Position::Right => (30, 10, 0, 0),
+        }
+    }
+}
+
+/// The geometry of the bar's layer surface
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarConfig {
+    #[serde(deserialize_with = "deserialize_layer")]
+    pub layer: Layer,
+    pub position: Position,
+    /// The thickness of the bar, its height when horizontal and its width when vertical
+    #[serde(alias = "height", deserialize_with = "deserialize_size")]
+    pub size: u32,
+    /// top, right, bottom, left
+    pub margin: (i32, i32, i32, i32),
+    /// None if the exclusive zone should be the same as the size
+    pub exclusive_zone: Option<i32>,
+    pub autohide: AutohideConfig,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            layer: Layer::Top,
+            position: Position::Top,
+            size: 50,
+            margin: (5, 10, 5, 10),
+            exclusive_zone: None,
+            autohide: AutohideConfig::default()
+        }
+    }
+}
+
+impl BarConfig {
+    /// Autohiding bars never reserve space, they are drawn over the windows when expanded
+    pub fn exclusive_zone(&self) -> i32 {
+        if self.autohide.enabled {
+            0
+        } else {
+            self.exclusive_zone.unwrap_or(self.size as i32)
+        }
+    }
+
+    /// The surface size of a hidden autohiding bar, a sliver along the edge
+    pub fn collapsed_size(&self) -> (u32, u32) {
+        if self.position.is_vertical() {
+            (self.autohide.size, 0)
+        } else {
+            (0, self.autohide.size)
+        }
+    }
+
+    /// The margin of a hidden autohiding bar, the sliver touches the screen edge so the pointer can reach it
+    pub fn collapsed_margin(&self) -> (i32, i32, i32, i32) {
+        let (top, right, bottom, left) = self.margin;
+        match self.position {
+            Position::Top => (0, right, bottom, left),
+            Position::Bottom => (top, right, 0, left),
+            Position::Left => (top, right, bottom, 0),
+            Position::Right => (top, 0, bottom, left),
+        }
+    }
+
+    /// The size of the layer surface, 0 along the anchored edges so it stretches over them
+    pub fn surface_size(&self) -> (u32, u32) {
+        if self.position.is_vertical() {
+            (self.size, 0)
+        } else {
+            (0, self.size)
+        }
+    }
+}
+
+/// Hiding the bar while the pointer is away from it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutohideConfig {
+    pub enabled: bool,
+    /// Milliseconds after the pointer left the bar before it's hidden
+    pub delay: u64,
+    /// The thickness of the hidden bar in pixels, the bar is shown when the pointer enters it
+    #[serde(deserialize_with = "deserialize_size")]
+    pub size: u32,
+}
+
+impl Default for AutohideConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delay: 1000,
+            size: 4
+        }
+    }
+}
+
+/// The modules shown in each section of the bar, from left to right.
+/// On vertical bars the sections go from top to bottom, so `top` and `bottom` can be used instead of `left` and `right`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    #[serde(alias = "top")]
+    pub left: Vec<Module>,
+    pub middle: Vec<Module>,
+    #[serde(alias = "bottom")]
+    pub right: Vec<Module>,
+}
+
+impl LayoutConfig {
+    pub fn contains(&self, module: Module) -> bool {
+        [&self.left, &self.middle, &self.right].into_iter().any(|section| section.contains(&module))
+    }
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            left: vec![Module::Clock, Module::Separator, Module::Weather, Module::Separator],
+            middle: Vec::new(),
+            right: Vec::new()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Levels in the `target=level` format of tracing's EnvFilter, like "warn,rusty_bar=info,rusty_bar::modules::media=debug"
+    #[serde(deserialize_with = "deserialize_log_level")]
+    pub level: String,
+    /// Also write the log to a file under `$XDG_STATE_HOME/rusty-bar/`, rotated daily
+    pub file: bool,
+    /// The number of daily log files kept
+    #[serde(deserialize_with = "deserialize_positive_usize")]
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: String::from("warn,rusty_bar=info"),
+            file: false,
+            max_files: 7
+        }
+    }
+}
+
+/// A bar besides the main one, with its own `[bar]` and `[layout]` sections
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarInstanceConfig {
+    pub bar: BarConfig,
+    pub layout: LayoutConfig,
+    /// The outputs this bar is shown on, by name or description. None if it's shown on every output
+    pub outputs: Option<Vec<String>>,
+}
+
+/// Overrides of the bar on a single output.
+/// Disabling an output removes every bar from it, the size and layout only override the main bar
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// False if there should be no bar on this output
+    pub enabled: bool,
+    #[serde(alias = "height", deserialize_with = "deserialize_optional_size")]
+    pub size: Option<u32>,
+    pub layout: Option<LayoutConfig>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: None,
+            layout: None
+        }
+    }
+}
+
+/// The bar of one output, with the overrides of its `[outputs]` section applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputBar {
+    pub bar: BarConfig,
+    pub layout: LayoutConfig,
+}
+
+impl Config {
+    /// The `[outputs]` section of an output, matched by name first, then by description
+    fn output(&self, name: &str, description: &str) -> Option<&OutputConfig> {
+        self.outputs.get(name).or_else(|| self.outputs.get(description))
+    }
+
+    /// The names of every bar, the main one first
+    pub fn bar_names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(MAIN_BAR).chain(self.bars.keys().map(String::as_str))
+    }
+
+    /// The layout of a bar on an output, without copying it like [Config::output_bar]
+    pub fn output_layout(&self, bar: &str, name: &str, description: &str) -> &LayoutConfig {
+        if let Some(instance) = self.bars.get(bar) {
+            return &instance.layout;
+        }
+
+        self.output(name, description)
+            .and_then(|output| output.layout.as_ref())
+            .unwrap_or(&self.layout)
+    }
+
+    /// A bar on an output, or None if it's not shown there
+    pub fn output_bar(&self, bar: &str, name: &str, description: &str) -> Option<OutputBar> {
+        let output = self.output(name, description);
+        if output.is_some_and(|output| !output.enabled) {
+            return None;
+        }
+
+        if bar != MAIN_BAR {
+            let instance = self.bars.get(bar)?;
+            if let Some(outputs) = &instance.outputs
+                && !outputs.iter().any(|output| output == name || output == description)
+            {
+                return None;
+            }
+
+            return Some(OutputBar {
+                bar: instance.bar.clone(),
+                layout: instance.layout.clone()
+            });
+        }
+
+        let mut output_bar = OutputBar {
+            bar: self.bar.clone(),
+            layout: self.layout.clone()
+        };
+
+        let Some(output) = output else {
+            return Some(output_bar);
+        };
+
+        if let Some(size) = output.size {
+            output_bar.bar.size = size;
+        }
+        if let Some(layout) = &output.layout {
+            output_bar.layout = layout.clone();
+        }
+
+        Some(output_bar)
+    }
+
+    /// The color scheme of the desktop is only followed if a light or dark theme is set
+    pub fn follows_color_scheme(&self) -> bool {
+        self.light_theme.is_some() || self.dark_theme.is_some()
+    }
+
+    /// The theme for the color scheme preferred by the desktop
+    pub fn current_theme(&self, scheme: ColorScheme) -> &Theme {
+        let theme = match scheme {
+            ColorScheme::Light => self.light_theme.as_ref(),
+            ColorScheme::Dark => self.dark_theme.as_ref(),
+            ColorScheme::NoPreference => None
+        };
+
+        theme.unwrap_or(&self.theme)
+    }
+
+    /// `$XDG_CONFIG_HOME/rusty-bar/config.toml`, or `~/.config/rusty-bar/config.toml` if XDG_CONFIG_HOME is unset
+    pub fn default_path() -> PathBuf {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                let home = std::env::var_os("HOME").unwrap_or_default();
+                PathBuf::from(home).join(".config")
+            });
+
+        config_home.join("rusty-bar").join("config.toml")
+    }
+
+    /// Reads and validates the config at `path`. If the file doesn't exist the default config is returned
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(source) => Self::parse(&source, path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(ConfigError::Io { path: path.to_path_buf(), source: Arc::new(source) })
+        }
+    }
+
+    /// Parses a config from a string, `path` is only used in error messages
+    pub fn parse(source: &str, path: &Path) -> Result<Self, ConfigError> {
+        toml::from_str(source).map_err(|e| {
+            let (line, column) = line_column(source, e.span());
+            ConfigError::Invalid {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message: e.message().trim_end().to_string()
+            }
+        })
+    }
+}
+
+/// Converts a byte span of the source into a 1 based line and column
+fn line_column(source: &str, span: Option<Range<usize>>) -> (usize, usize) {
+    let offset = span.map(|span| span.start).unwrap_or(0).min(source.len());
+    let before = &source[..offset];
+
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+fn deserialize_time_format<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    use chrono::format::{Item, StrftimeItems};
+
+    let format = String::deserialize(deserializer)?;
+    if StrftimeItems::new(&format).any(|item| matches!(item, Item::Error)) {
+        return Err(D::Error::custom(format!("invalid time format \"{format}\"")));
+    }
+    Ok(format)
+}
+
+fn deserialize_optional_time_format<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    deserialize_time_format(deserializer).map(Some)
+}
+
+fn deserialize_time_zone<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Tz, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse()
+        .map_err(|_| D::Error::custom(format!("unknown time zone \"{name}\", expected an IANA name like \"America/New_York\"")))
+}
+
+fn deserialize_schedules<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Schedule>, D::Error> {
+    let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok();
+
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|schedule| {
+            schedule.split_once('-')
+                .and_then(|(start, end)| Some(Schedule { start: parse(start)?, end: parse(end)? }))
+                .filter(|schedule| schedule.start != schedule.end)
+                .ok_or_else(|| D::Error::custom(format!("invalid schedule \"{schedule}\", expected a range like \"22:00-07:00\"")))
+        })
+        .collect()
+}
+
+fn deserialize_paths<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> {
+    let paths = Vec::<PathBuf>::deserialize(deserializer)?;
+    Ok(paths.iter().map(|path| theme::expand_home(path)).collect())
+}
+
+fn deserialize_bars<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, BarInstanceConfig>, D::Error> {
+    let bars = BTreeMap::<String, BarInstanceConfig>::deserialize(deserializer)?;
+    if bars.contains_key(MAIN_BAR) {
+        return Err(D::Error::custom(format!("\"{MAIN_BAR}\" is the bar of the top level [bar] and [layout] sections, choose another name")));
+    }
+    Ok(bars)
+}
+
+fn deserialize_log_level<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let level = String::deserialize(deserializer)?;
+    tracing_subscriber::EnvFilter::try_new(&level)
+        .map_err(|e| D::Error::custom(format!("invalid log level \"{level}\": {e}")))?;
+    Ok(level)
+}
+
+fn deserialize_location<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Coordinates>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Location {
+        latitude: f64,
+        longitude: f64
+    }
+
+    let Location { latitude, longitude } = Location::deserialize(deserializer)?;
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Err(D::Error::custom(format!("latitude {latitude} is out of range, it must be between -90 and 90")));
+    }
+    if !(-180.0..=180.0).contains(&longitude) {
+        return Err(D::Error::custom(format!("longitude {longitude} is out of range, it must be between -180 and 180")));
+    }
+
+    Ok(Some(Coordinates::new(longitude, latitude)))
+}
+
+fn deserialize_layer<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Layer, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    match name.as_str() {
+        "background" => Ok(Layer::Background),
+        "bottom" => Ok(Layer::Bottom),
+        "top" => Ok(Layer::Top),
+        "overlay" => Ok(Layer::Overlay),
+        _ => Err(D::Error::custom(format!("unknown layer \"{name}\", expected one of \"background\", \"bottom\", \"top\", \"overlay\"")))
+    }
+}
+
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let size = u32::deserialize(deserializer)?;
+    if size == 0 {
+        return Err(D::Error::custom("size must be greater than 0"));
+    }
+    Ok(size)
+}
+
+fn deserialize_optional_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    deserialize_size(deserializer).map(Some)
+}
+
+fn deserialize_positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let value = u32::deserialize(deserializer)?;
+    if value == 0 {
+        return Err(D::Error::custom("must be greater than 0"));
+    }
+    Ok(value)
+}
+
+fn deserialize_positive_usize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let value = usize::deserialize(deserializer)?;
+    if value == 0 {
+        return Err(D::Error::custom("must be greater than 0"));
+    }
+    Ok(value)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_matches_default() {
+        let parsed = Config::parse(DEFAULT_CONFIG, Path::new("default_config.toml")).unwrap();
+        assert_eq!(parsed, Config::default());
+    }
+
+    #[test]
+    fn empty_config_is_default() {
+        let parsed = Config::parse("", Path::new("config.toml")).unwrap();
+        assert_eq!(parsed, Config::default());
+    }
+
+    #[test]
+    fn error_names_the_line() {
+        let source = "radius = 4\n\n[clock]\nformat = \"%H:%Q\"\n";
+
+        match Config::parse(source, Path::new("config.toml")) {
+            Err(ConfigError::Invalid { line, message, .. }) => {
+                assert_eq!(line, 4);
+                assert!(message.contains("invalid time format"), "{message}");
+            },
+            other => panic!("expected an Invalid error, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn clock_zones() {
+        let source = "[clock]\nzones = [\n    { name = \"NYC\", timezone = \"America/New_York\" },\n    { name = \"Tokyo\", timezone = \"Asia/Tokyo\", format = \"%H:%M\" },\n]";
+        let zones = Config::parse(source, Path::new("config.toml")).unwrap().clock.zones;
+
+        assert_eq!(zones[0].timezone, chrono_tz::America::New_York);
+        assert_eq!(zones[0].format, None);
+        assert_eq!(zones[1].format.as_deref(), Some("%H:%M"));
+    }
+
+    #[test]
+    fn unknown_time_zone_rejected() {
+        let source = "[clock]\nzones = [\n    { name = \"Home\", timezone = \"Europe/Atlantis\" },\n]";
+
+        match Config::parse(source, Path::new("config.toml")) {
+            Err(ConfigError::Invalid { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert!(message.contains("Europe/Atlantis"), "{message}");
+            },
+            other => panic!("expected an Invalid error, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn calendar_paths_expand_home() {
+        let source = "[calendar]\npaths = [\"~/.calendars/work\", \"/srv/holidays.ics\"]\nnext_event = true";
+        let calendar = Config::parse(source, Path::new("config.toml")).unwrap().calendar;
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap_or_default());
+
+        assert_eq!(calendar.paths, vec![home.join(".calendars/work"), PathBuf::from("/srv/holidays.ics")]);
+        assert!(calendar.next_event);
+        assert_eq!(calendar.next_event_within, 60);
+    }
+
+    #[test]
+    fn zero_timer_duration_rejected() {
+        let source = "[timer]\nwork = 50\nshort_break = 0";
+
+        match Config::parse(source, Path::new("config.toml")) {
+            Err(ConfigError::Invalid { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert!(message.contains("greater than 0"), "{message}");
+            },
+            other => panic!("expected an Invalid error, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn zero_log_files_rejected() {
+        let source = "[log]\nfile = true\nmax_files = 0";
+        match Config::parse(source, Path::new("config.toml")) {
+            Err(ConfigError::Invalid { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert!(message.contains("greater than 0"), "{message}");
+            },
+            other => panic!("expected an Invalid error, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn zero_notification_limits_rejected() {
+        for option in ["max_toasts", "toasts_per_minute", "max_entries"] {
+            let source = format!("[notifications]\nserver = true\n{option} = 0");
+
+            match Config::parse(&source, Path::new("config.toml")) {
+                Err(ConfigError::Invalid { line, message, .. }) => {
+                    assert_eq!(line, 3, "{option}");
+                    assert!(message.contains("greater than 0"), "{message}");
+                },
+                other => panic!("expected an Invalid error for {option}, got {other:?}")
+            }
+        }
+    }
+
+    #[test]
+    fn layout_keeps_order() {
+        let source = "[layout]\nleft = [\"weather\", \"separator\", \"clock\"]\nright = [\"media\"]";
+        let layout = Config::parse(source, Path::new("config.toml")).unwrap().layout;
+
+        assert_eq!(layout.left, vec![Module::Weather, Module::Separator, Module::Clock]);
+        assert!(layout.middle.is_empty());
+        assert_eq!(layout.right, vec![Module::Media]);
+    }
+
+    #[test]
+    fn unknown_module_rejected() {
+        let source = "[layout]\nleft = [\"clock\"]\nright = [\"battery\"]";
+        let result = Config::parse(source, Path::new("config.toml"));
+        assert!(matches!(result, Err(ConfigError::Invalid { line: 3, .. })));
+    }
+
+    #[test]
+    fn output_overrides() {
+        let source = "[outputs.\"eDP-1\"]\nsize = 30\nlayout = { left = [\"clock\"] }\n\n[outputs.\"HDMI-A-1\"]\nenabled = false";
+        let config = Config::parse(source, Path::new("config.toml")).unwrap();
+
+        let laptop = config.output_bar(MAIN_BAR, "eDP-1", "Built-in display").unwrap();
+        assert_eq!(laptop.bar.size, 30);
+        assert_eq!(laptop.layout.left, vec![Module::Clock]);
+        assert!(laptop.layout.right.is_empty());
+
+        assert_eq!(config.output_bar(MAIN_BAR, "HDMI-A-1", "Some monitor"), None);
+
+        let other = config.output_bar(MAIN_BAR, "DP-2", "Another monitor").unwrap();
+        assert_eq!(other, OutputBar { bar: BarConfig::default(), layout: LayoutConfig::default() });
+    }
+
+    #[test]
+    fn vertical_position() {
+        let source = "[bar]\nposition = \"left\"\nheight = 40\n\n[layout]\ntop = [\"clock\"]\nbottom = [\"weather\"]";
+        let config = Config::parse(source, Path::new("config.toml")).unwrap();
+
+        assert_eq!(config.bar.position, Position::Left);
+        assert_eq!(config.bar.surface_size(), (40, 0));
+        assert_eq!(config.bar.position.anchor(), Anchor::Left | Anchor::Top | Anchor::Bottom);
+        assert_eq!(config.layout.left, vec![Module::Clock]);
+        assert_eq!(config.layout.right, vec![Module::Weather]);
+    }
+
+    #[test]
+    fn autohide_reserves_no_space() {
+        let source = "[bar]\nposition = \"bottom\"\nexclusive_zone = 60\n\n[bar.autohide]\nenabled = true\nsize = 2";
+        let bar = Config::parse(source, Path::new("config.toml")).unwrap().bar;
+
+        assert_eq!(bar.exclusive_zone(), 0);
+        assert_eq!(bar.collapsed_size(), (0, 2));
+        assert_eq!(bar.collapsed_margin(), (5, 10, 0, 10));
+    }
+
+    #[test]
+    fn named_bars() {
+        let source = "[bars.bottom]\noutputs = [\"eDP-1\"]\nbar = { position = \"bottom\", size = 30 }\nlayout = { right = [\"media\"] }";
+        let config = Config::parse(source, Path::new("config.toml")).unwrap();
+
+        assert_eq!(config.bar_names().collect::<Vec<_>>(), vec![MAIN_BAR, "bottom"]);
+
+        let bottom = config.output_bar("bottom", "eDP-1", "Built-in display").unwrap();
+        assert_eq!(bottom.bar.position, Position::Bottom);
+        assert_eq!(bottom.bar.size, 30);
+        assert_eq!(config.output_layout("bottom", "eDP-1", "Built-in display").right, vec![Module::Media]);
+
+        assert_eq!(config.output_bar("bottom", "DP-2", "Another monitor"), None);
+        assert!(config.output_bar(MAIN_BAR, "DP-2", "Another monitor").is_some());
+    }
+
+    #[test]
+    fn main_bar_name_reserved() {
+        let result = Config::parse("[bars.main]\nlayout = { left = [\"clock\"] }", Path::new("config.toml"));
+        assert!(matches!(result, Err(ConfigError::Invalid { .. })));
+    }
+
+    #[test]
+    fn unknown_theme_rejected() {
+        let result = Config::parse("theme = \"not a theme\"", Path::new("config.toml"));
+        assert!(matches!(result, Err(ConfigError::Invalid { line: 1, .. })));
+    }
+
+    #[test]
+    fn dnd_schedules() {
+        let config = Config::parse("[notifications.dnd]\nschedules = [\"22:00-07:00\", \" 12:30 - 13:00 \"]", Path::new("config.toml")).unwrap();
+        let [night, lunch] = config.notifications.dnd.schedules[..] else {
+            panic!("two schedules are parsed");
+        };
+        let time = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+
+        assert!(night.contains(time(23, 0)) && night.contains(time(3, 0)) && !night.contains(time(7, 0)));
+        assert!(lunch.contains(time(12, 45)) && !lunch.contains(time(13, 0)) && !lunch.contains(time(23, 0)));
+
+        for schedule in ["22:00", "22:00-25:00", "10:00-10:00"] {
+            let source = format!("[notifications.dnd]\nschedules = [\"{schedule}\"]");
+            assert!(Config::parse(&source, Path::new("config.toml")).is_err(), "{schedule}");
+        }
+    }
+
+    #[test]
+    fn custom_palette() {
+        let source = "theme = { name = \"Mine\", background = \"#101010\", primary = \"#ff0000\" }";
+        let theme = Config::parse(source, Path::new("config.toml")).unwrap().theme;
+
+        assert_eq!(theme.to_string(), "Mine");
+        assert_eq!(theme.palette().background, iced::Color::from_rgb8(0x10, 0x10, 0x10));
+        assert_eq!(theme.palette().text, Theme::CatppuccinMocha.palette().text);
+    }
+
+    #[test]
+    fn invalid_color_rejected() {
+        let result = Config::parse("\n[theme]\nprimary = \"red\"", Path::new("config.toml"));
+        assert!(matches!(result, Err(ConfigError::Invalid { line: 3, .. })));
+    }
+
+    #[test]
+    fn theme_follows_color_scheme() {
+        let source = "theme = \"Tokyo Night\"\nlight_theme = \"Catppuccin Latte\"";
+        let config = Config::parse(source, Path::new("config.toml")).unwrap();
+
+        assert!(config.follows_color_scheme());
+        assert_eq!(config.current_theme(ColorScheme::Light), &Theme::CatppuccinLatte);
+        assert_eq!(config.current_theme(ColorScheme::Dark), &Theme::TokyoNight);
+        assert_eq!(config.current_theme(ColorScheme::NoPreference), &Theme::TokyoNight);
+    }
+
+    #[test]
+    fn theme_name_is_normalized() {
+        let parsed = Config::parse("theme = \"catppuccin-latte\"", Path::new("config.toml")).unwrap();
+        assert_eq!(parsed.theme, Theme::CatppuccinLatte);
+    }
+}
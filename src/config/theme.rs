@@ -0,0 +1,298 @@
+//! The `theme` option, either the name of a built in iced theme or a table with a custom palette.
+//! Custom palettes can be imported from a base16 scheme or the `colors.json` of pywal, and single colors overridden
+
+use std::{fmt, path::{Path, PathBuf}};
+
+use iced::{Color, Theme, theme::Palette};
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, MapAccess, Visitor, value::MapAccessDeserializer}
+};
+
+/// The table form of the theme option
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CustomTheme {
+    name: Option<String>,
+    /// A base16 scheme yaml file
+    base16: Option<PathBuf>,
+    /// A pywal colors.json file, usually `~/.cache/wal/colors.json`
+    pywal: Option<PathBuf>,
+
+    #[serde(default, deserialize_with = "deserialize_color")]
+    background: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    text: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    primary: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    success: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    warning: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    danger: Option<Color>,
+}
+
+impl CustomTheme {
+    /// Imports the palette file if there is one, and applies the colors set in the table on top of it.
+    /// Without a file, the colors that aren't set come from the default theme
+    fn build(self) -> Result<Theme, String> {
+        let (name, mut palette) = match (&self.base16, &self.pywal) {
+            (Some(_), Some(_)) => return Err(String::from("a theme can be imported from base16 or pywal, not both")),
+            (Some(path), None) => read_base16(&expand_home(path))?,
+            (None, Some(path)) => read_pywal(&expand_home(path))?,
+            (None, None) => (String::from("Custom"), Theme::CatppuccinMocha.palette())
+        };
+
+        let overrides = [
+            (self.background, &mut palette.background),
+            (self.text, &mut palette.text),
+            (self.primary, &mut palette.primary),
+            (self.success, &mut palette.success),
+            (self.warning, &mut palette.warning),
+            (self.danger, &mut palette.danger),
+        ];
+        for (color, target) in overrides {
+            if let Some(color) = color {
+                *target = color;
+            }
+        }
+
+        Ok(Theme::custom(self.name.unwrap_or(name), palette))
+    }
+}
+
+pub fn deserialize_theme<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Theme, D::Error> {
+    struct ThemeVisitor;
+
+    impl<'de> Visitor<'de> for ThemeVisitor {
+        type Value = Theme;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("the name of a theme, or a table with a custom palette")
+        }
+
+        fn visit_str<E: de::Error>(self, name: &str) -> Result<Theme, E> {
+            builtin(name).ok_or_else(|| {
+                let known: Vec<String> = Theme::ALL.iter().map(|theme| format!("\"{theme}\"")).collect();
+                E::custom(format!("unknown theme \"{name}\", expected one of {}", known.join(", ")))
+            })
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Theme, A::Error> {
+            CustomTheme::deserialize(MapAccessDeserializer::new(map))?
+                .build()
+                .map_err(de::Error::custom)
+        }
+    }
+
+    deserializer.deserialize_any(ThemeVisitor)
+}
+
+pub fn deserialize_optional_theme<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Theme>, D::Error> {
+    deserialize_theme(deserializer).map(Some)
+}
+
+/// Lowercase without spaces, dashes and underscores, so "Catppuccin Mocha" == "catppuccin-mocha"
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The built in theme with this name
+fn builtin(name: &str) -> Option<Theme> {
+    Theme::ALL
+        .iter()
+        .find(|theme| normalize(&theme.to_string()) == normalize(name))
+        .cloned()
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    parse_hex(&hex)
+        .map(Some)
+        .ok_or_else(|| de::Error::custom(format!("invalid color \"{hex}\", expected a hex color like \"#1e1e2e\"")))
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`, the `#` is optional
+fn parse_hex(hex: &str) -> Option<Color> {
+    let hex = hex.trim().trim_start_matches('#');
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let (r, g, b) = (channel(0)?, channel(2)?, channel(4)?);
+
+    if hex.len() == 8 {
+        Some(Color::from_rgba8(r, g, b, channel(6)? as f32 / 255.0))
+    } else {
+        Some(Color::from_rgb8(r, g, b))
+    }
+}
+
+/// Replaces a leading `~` with the home directory
+pub(super) fn expand_home(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(rest),
+        Err(_) => path.to_path_buf()
+    }
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))
+}
+
+fn read_base16(path: &Path) -> Result<(String, Palette), String> {
+    parse_base16(&read(path)?).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn read_pywal(path: &Path) -> Result<(String, Palette), String> {
+    parse_pywal(&read(path)?).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Reads the name and colors of a base16 scheme.
+/// Only the flat `key: "value"` lines are needed, so both the original format and the newer one,
+/// where the colors are nested under `palette:`, are read without a yaml parser
+fn parse_base16(source: &str) -> Result<(String, Palette), String> {
+    let value = |key: &str| {
+        source.lines().find_map(|line| {
+            let (name, value) = line.trim().split_once(':')?;
+            (name.trim() == key).then(|| {
+                // Drop comments after the value, then the quotes
+                let value = value.split(" #").next().unwrap_or(value);
+                value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()
+            })
+        })
+    };
+    let color = |key: &str| {
+        let hex = value(key).ok_or_else(|| format!("{key} is missing"))?;
+        parse_hex(&hex).ok_or_else(|| format!("{key} is not a hex color: \"{hex}\""))
+    };
+
+    let name = value("scheme").or_else(|| value("name")).unwrap_or_else(|| String::from("base16"));
+
+    // The usual base16 roles: default background, default foreground, blue, green, yellow and red
+    let palette = Palette {
+        background: color("base00")?,
+        text: color("base05")?,
+        primary: color("base0D")?,
+        success: color("base0B")?,
+        warning: color("base0A")?,
+        danger: color("base08")?,
+    };
+
+    Ok((name, palette))
+}
+
+/// Reads the colors of a pywal `colors.json`
+fn parse_pywal(source: &str) -> Result<(String, Palette), String> {
+    #[derive(Deserialize)]
+    struct Special {
+        background: String,
+        foreground: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Colors {
+        special: Special,
+        colors: std::collections::HashMap<String, String>,
+    }
+
+    let colors: Colors = serde_json::from_str(source).map_err(|e| e.to_string())?;
+
+    let parse = |key: &str, hex: Option<&String>| {
+        let hex = hex.ok_or_else(|| format!("{key} is missing"))?;
+        parse_hex(hex).ok_or_else(|| format!("{key} is not a hex color: \"{hex}\""))
+    };
+    let color = |key: &str| parse(key, colors.colors.get(key));
+
+    // The terminal colors 4, 2, 3 and 1 are blue, green, yellow and red, before pywal shifts them towards the wallpaper
+    let palette = Palette {
+        background: parse("background", Some(&colors.special.background))?,
+        text: parse("foreground", Some(&colors.special.foreground))?,
+        primary: color("color4")?,
+        success: color("color2")?,
+        warning: color("color3")?,
+        danger: color("color1")?,
+    };
+
+    Ok((String::from("pywal"), palette))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex("#ff8000"), Some(Color::from_rgb8(255, 128, 0)));
+        assert_eq!(parse_hex("ff8000"), Some(Color::from_rgb8(255, 128, 0)));
+        assert_eq!(parse_hex("#ff800080"), Some(Color::from_rgba8(255, 128, 0, 128.0 / 255.0)));
+        assert_eq!(parse_hex("#ff80"), None);
+        assert_eq!(parse_hex("#gg8000"), None);
+    }
+
+    #[test]
+    fn base16_scheme() {
+        let source = "\
+scheme: \"Tomorrow Night\"
+author: \"Chris Kempson\"
+base00: \"1d1f21\"
+base01: \"282a2e\"
+base05: \"c5c8c6\" # foreground
+base08: \"cc6666\"
+base0A: \"f0c674\"
+base0B: \"b5bd68\"
+base0D: \"81a2be\"
+";
+        let (name, palette) = parse_base16(source).unwrap();
+        assert_eq!(name, "Tomorrow Night");
+        assert_eq!(palette.background, Color::from_rgb8(0x1d, 0x1f, 0x21));
+        assert_eq!(palette.text, Color::from_rgb8(0xc5, 0xc8, 0xc6));
+        assert_eq!(palette.primary, Color::from_rgb8(0x81, 0xa2, 0xbe));
+        assert_eq!(palette.danger, Color::from_rgb8(0xcc, 0x66, 0x66));
+    }
+
+    #[test]
+    fn base16_nested_palette() {
+        let source = "\
+system: \"base16\"
+name: \"Nested\"
+palette:
+  base00: \"#000000\"
+  base05: \"#ffffff\"
+  base08: \"#ff0000\"
+  base0A: \"#ffff00\"
+  base0B: \"#00ff00\"
+  base0D: \"#0000ff\"
+";
+        let (name, palette) = parse_base16(source).unwrap();
+        assert_eq!(name, "Nested");
+        assert_eq!(palette.success, Color::from_rgb8(0, 255, 0));
+    }
+
+    #[test]
+    fn base16_missing_color() {
+        let error = parse_base16("base00: \"000000\"").unwrap_err();
+        assert!(error.contains("base05"), "{error}");
+    }
+
+    #[test]
+    fn pywal_colors() {
+        let source = r##"{
+            "wallpaper": "/home/user/wall.png",
+            "special": { "background": "#0f1011", "foreground": "#c3c4c4", "cursor": "#c3c4c4" },
+            "colors": {
+                "color0": "#0f1011", "color1": "#5C6467", "color2": "#6E777A", "color3": "#7E8487",
+                "color4": "#8A9296", "color5": "#9AA1A3", "color6": "#A6ACAE", "color7": "#c3c4c4"
+            }
+        }"##;
+        let (_, palette) = parse_pywal(source).unwrap();
+        assert_eq!(palette.background, Color::from_rgb8(0x0f, 0x10, 0x11));
+        assert_eq!(palette.primary, Color::from_rgb8(0x8a, 0x92, 0x96));
+        assert_eq!(palette.danger, Color::from_rgb8(0x5c, 0x64, 0x67));
+    }
+}
@@ -0,0 +1,54 @@
+use std::{path::PathBuf, sync::Arc};
+
+use futures::{SinkExt, Stream, StreamExt};
+use iced::stream;
+use inotify::{Inotify, WatchMask};
+use tracing::{error, warn};
+
+use super::{Config, ConfigError};
+
+/// Watches the config file with inotify, and yields the reparsed config every time it's written, moved into place or deleted.
+/// The parent directory is watched instead of the file, because most editors replace the file instead of writing into it
+pub fn watch(path: &PathBuf) -> impl Stream<Item = Result<Config, ConfigError>> + use<> {
+    let path = path.clone();
+
+    stream::channel(8, async move |mut output| {
+        let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+            warn!("Not watching config {}: it has no parent directory", path.display());
+            return;
+        };
+
+        let inotify = match Inotify::init() {
+            Ok(inotify) => inotify,
+            Err(e) => {
+                error!("Failed to initialise inotify: {e}");
+                return;
+            }
+        };
+
+        if let Err(e) = inotify.watches().add(dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::DELETE) {
+            error!("Failed to watch config directory {}: {e}", dir.display());
+            return;
+        }
+
+        let mut events = match inotify.into_event_stream([0; 1024]) {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Failed to create inotify event stream: {e}");
+                return;
+            }
+        };
+
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(event) if event.name.as_deref() == Some(file_name) => {
+                    let _ = output.send(Config::load(&path)).await;
+                },
+                Ok(_) => {},
+                Err(e) => {
+                    let _ = output.send(Err(ConfigError::Io { path: path.clone(), source: Arc::new(e) })).await;
+                }
+            }
+        }
+    })
+}
@@ -145,7 +145,7 @@ impl<Message> Program<Message> for Graph {
                         .min_by(|a, b| {
                             let da = (point.x - a.0).abs();
                             let db = (point.x - b.0).abs();
-                            da.partial_cmp(&db).unwrap()
+                            da.total_cmp(&db)
                         });
 
                     state.closest_index = closest.cloned();
@@ -1,7 +1,6 @@
 use crate::graph::graph::*;
 use iced::{Color, Font, widget::Canvas};
 use core::f32;
-use std::str::FromStr;
 
 pub fn graph<'a>(
     scale_line_color: Color,
@@ -93,7 +92,7 @@ pub fn graph<'a>(
         series_line_width: serires_line_width.into(),
 
         hover_radius: 9.0,
-        hover_color: hover_color.unwrap_or(Color::from_str("#cba6f7").expect("Failed to convert '#cba6f7' to color"))
+        hover_color: hover_color.unwrap_or(Color::from_rgb8(0xcb, 0xa6, 0xf7))
     };
 
     Canvas::new(graph)
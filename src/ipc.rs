@@ -0,0 +1,325 @@
+//! Control of a running bar over a Unix socket, so it can be scripted from compositor keybindings.
+//! Every line written to the socket is a command, and every command gets a single line JSON [Reply]
+
+use std::{fmt, io, path::PathBuf, sync::{Arc, Mutex}, time::Duration};
+
+use futures::{SinkExt, Stream, channel::oneshot};
+use iced::stream;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info, warn};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream}
+};
+
+#[derive(Debug, Error)]
+pub enum IpcError {
+    #[error("XDG_RUNTIME_DIR is not set")]
+    NoRuntimeDir,
+    #[error("unknown command \"{0}\", expected one of {known}", known = Command::known())]
+    UnknownCommand(String),
+    #[error("invalid age \"{0}\", expected a number followed by s, m, h or d, like 30m")]
+    InvalidAge(String),
+    #[error("no bar is listening on {}", .0.display())]
+    NotRunning(PathBuf),
+    #[error("Socket error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid reply from the bar: {0}")]
+    InvalidReply(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    WeatherRefresh,
+    WeatherToggle,
+    Reload,
+    Hide,
+    Show,
+    ToggleVisibility,
+    TimerToggle,
+    TimerReset,
+    TimerMode,
+    TimerSkip,
+    DndOn,
+    DndOff,
+    DndToggle,
+    /// The notifications of the history newer than the age, or all of them
+    NotificationsList(Option<Duration>),
+}
+
+impl Command {
+    pub const ALL: &[Self] = &[
+        Self::WeatherRefresh,
+        Self::WeatherToggle,
+        Self::Reload,
+        Self::Hide,
+        Self::Show,
+        Self::ToggleVisibility,
+        Self::TimerToggle,
+        Self::TimerReset,
+        Self::TimerMode,
+        Self::TimerSkip,
+        Self::DndOn,
+        Self::DndOff,
+        Self::DndToggle,
+        Self::NotificationsList(None)
+    ];
+
+    /// Every command quoted, for error messages
+    fn known() -> String {
+        let known: Vec<String> = Self::ALL.iter().map(|command| format!("\"{command}\"")).collect();
+        known.join(", ")
+    }
+
+    /// Parses a command, words can be separated by any amount of whitespace
+    pub fn parse(line: &str) -> Result<Self, IpcError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["notifications", "list"] => return Ok(Self::NotificationsList(None)),
+            ["notifications", "list", "--since", age] => return parse_age(age).map(|age| Self::NotificationsList(Some(age))),
+            _ => {}
+        }
+
+        Self::ALL
+            .iter()
+            .find(|command| command.to_string().split(' ').eq(words.iter().copied()))
+            .copied()
+            .ok_or_else(|| IpcError::UnknownCommand(words.join(" ")))
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::WeatherRefresh => "weather refresh",
+            Self::WeatherToggle => "weather toggle",
+            Self::Reload => "reload",
+            Self::Hide => "hide",
+            Self::Show => "show",
+            Self::ToggleVisibility => "toggle-visibility",
+            Self::TimerToggle => "timer toggle",
+            Self::TimerReset => "timer reset",
+            Self::TimerMode => "timer mode",
+            Self::TimerSkip => "timer skip",
+            Self::DndOn => "dnd on",
+            Self::DndOff => "dnd off",
+            Self::DndToggle => "dnd toggle",
+            Self::NotificationsList(None) => "notifications list",
+            Self::NotificationsList(Some(age)) => return write!(f, "notifications list --since {}s", age.as_secs()),
+        })
+    }
+}
+
+/// An age like `90s`, `30m`, `1h` or `7d`
+fn parse_age(age: &str) -> Result<Duration, IpcError> {
+    let invalid = || IpcError::InvalidAge(age.to_string());
+
+    let unit = age.chars().last().ok_or_else(invalid)?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(invalid())
+    };
+    let count: u64 = age[..age.len() - 1].parse().map_err(|_| invalid())?;
+
+    count.checked_mul(seconds).map(Duration::from_secs).ok_or_else(invalid)
+}
+
+/// The answer to a single command, written to the socket as one line of JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reply {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// What the command asked for, for the commands that query the bar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl Reply {
+    pub fn ok() -> Self {
+        Self { ok: true, error: None, data: None }
+    }
+
+    pub fn error(error: impl ToString) -> Self {
+        Self { ok: false, error: Some(error.to_string()), data: None }
+    }
+
+    pub fn data(data: impl Serialize) -> Self {
+        match serde_json::to_value(data) {
+            Ok(data) => Self { ok: true, error: None, data: Some(data) },
+            Err(e) => Self::error(e)
+        }
+    }
+}
+
+/// Sends the reply of a [Request] back to the socket connection it came from.
+/// Only the first reply is sent, the rest are ignored
+#[derive(Clone)]
+pub struct Responder(Arc<Mutex<Option<oneshot::Sender<Reply>>>>);
+
+impl Responder {
+    pub fn send(&self, reply: Reply) {
+        let sender = self.0.lock().ok().and_then(|mut sender| sender.take());
+        if let Some(sender) = sender {
+            let _ = sender.send(reply);
+        }
+    }
+}
+
+impl fmt::Debug for Responder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Responder")
+    }
+}
+
+/// A command received on the socket, waiting to be answered by the bar
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub command: Command,
+    pub reply: Responder,
+}
+
+/// `$XDG_RUNTIME_DIR/rusty-bar-$WAYLAND_DISPLAY.sock`, so bars of different sessions don't share a socket
+pub fn socket_path() -> Result<PathBuf, IpcError> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .ok_or(IpcError::NoRuntimeDir)?;
+    let display = std::env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| String::from("wayland-0"));
+
+    Ok(PathBuf::from(runtime_dir).join(format!("rusty-bar-{display}.sock")))
+}
+
+/// Binds the socket, replacing it if it was left behind by a bar that's no longer running
+fn bind(path: &PathBuf) -> Result<UnixListener, IpcError> {
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(IpcError::Io(io::Error::new(io::ErrorKind::AddrInUse, format!("another bar is listening on {}", path.display()))));
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(UnixListener::bind(path)?)
+}
+
+/// Listens on the socket, and yields a [Request] for every valid command.
+/// Invalid commands are answered here, without reaching the bar
+pub fn listen() -> impl Stream<Item = Request> {
+    stream::channel(8, async move |output| {
+        let listener = match socket_path().and_then(|path| bind(&path).map(|listener| (path, listener))) {
+            Ok((path, listener)) => {
+                info!(path = %path.display(), "Listening for commands");
+                listener
+            },
+            Err(e) => {
+                error!("Failed to open the command socket: {e}");
+                return;
+            }
+        };
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, output.clone()));
+                },
+                Err(e) => warn!("Failed to accept a connection on the command socket: {e}")
+            }
+        }
+    })
+}
+
+/// Answers the commands of a single connection, one line at a time
+async fn handle_connection(stream: UnixStream, mut output: futures::channel::mpsc::Sender<Request>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match Command::parse(&line) {
+            Ok(command) => {
+                let (sender, receiver) = oneshot::channel();
+                let request = Request { command, reply: Responder(Arc::new(Mutex::new(Some(sender)))) };
+
+                if output.send(request).await.is_err() {
+                    Reply::error("the bar is shutting down")
+                } else {
+                    receiver.await.unwrap_or_else(|_| Reply::error("the bar didn't answer the command"))
+                }
+            },
+            Err(e) => Reply::error(e)
+        };
+
+        let Ok(mut json) = serde_json::to_string(&reply) else {
+            break;
+        };
+        json.push('\n');
+
+        if writer.write_all(json.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Sends a command to the running bar and waits for its reply.
+/// Used by `rusty_bar msg`, outside of the iced runtime, so it's blocking
+pub fn send(command: &str) -> Result<Reply, IpcError> {
+    use std::{io::{BufRead, Write}, os::unix::net::UnixStream};
+
+    let path = socket_path()?;
+    let mut stream = UnixStream::connect(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => IpcError::NotRunning(path.clone()),
+        _ => IpcError::Io(e)
+    })?;
+
+    writeln!(stream, "{command}")?;
+
+    let mut reply = String::new();
+    io::BufReader::new(stream).read_line(&mut reply)?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_roundtrip() {
+        for command in Command::ALL {
+            assert_eq!(Command::parse(&command.to_string()).unwrap(), *command);
+        }
+    }
+
+    #[test]
+    fn extra_whitespace_is_ignored() {
+        assert_eq!(Command::parse("  weather \t refresh\n").unwrap(), Command::WeatherRefresh);
+    }
+
+    #[test]
+    fn unknown_command_rejected() {
+        assert!(matches!(Command::parse("weather"), Err(IpcError::UnknownCommand(_))));
+        assert!(matches!(Command::parse("weather refresh now"), Err(IpcError::UnknownCommand(_))));
+    }
+
+    #[test]
+    fn notifications_since() {
+        assert_eq!(Command::parse("notifications list --since 1h").unwrap(), Command::NotificationsList(Some(Duration::from_secs(3600))));
+        assert_eq!(Command::parse("notifications list --since 90s").unwrap().to_string(), "notifications list --since 90s");
+        assert!(matches!(Command::parse("notifications list --since 1w"), Err(IpcError::InvalidAge(_))));
+        assert!(matches!(Command::parse("notifications list --since h"), Err(IpcError::InvalidAge(_))));
+        assert!(matches!(Command::parse("notifications list --since"), Err(IpcError::UnknownCommand(_))));
+    }
+
+    #[test]
+    fn reply_json() {
+        assert_eq!(serde_json::to_string(&Reply::ok()).unwrap(), r#"{"ok":true}"#);
+        assert_eq!(serde_json::to_string(&Reply::error("nope")).unwrap(), r#"{"ok":false,"error":"nope"}"#);
+        assert_eq!(serde_json::to_string(&Reply::data([1, 2])).unwrap(), r#"{"ok":true,"data":[1,2]}"#);
+    }
+}
@@ -0,0 +1,93 @@
+//! Logging with tracing, to stderr and optionally to a daily rotated file under `$XDG_STATE_HOME/rusty-bar/`.
+//! The levels come from the `RUSTY_BAR_LOG` environment variable if it's set, otherwise from the `[log]` section of the config
+
+use std::{path::PathBuf, sync::OnceLock};
+
+use tracing_appender::{non_blocking::WorkerGuard, rolling::{Builder, Rotation}};
+use tracing_subscriber::{EnvFilter, Registry, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt};
+
+use crate::config::LogConfig;
+
+/// Overrides the level of the config, in the same `target=level` format
+pub const ENV_VAR: &str = "RUSTY_BAR_LOG";
+
+/// Swaps the filter when the config is reloaded
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// `$XDG_STATE_HOME/rusty-bar`, or `~/.local/state/rusty-bar` if XDG_STATE_HOME is unset
+pub fn state_dir() -> PathBuf {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = std::env::var_os("HOME").unwrap_or_default();
+            PathBuf::from(home).join(".local").join("state")
+        });
+
+    state_home.join("rusty-bar")
+}
+
+/// The filter of the environment variable, or the one of the config if it's unset or invalid
+fn filter(config: &LogConfig) -> EnvFilter {
+    if let Ok(directives) = std::env::var(ENV_VAR) {
+        match EnvFilter::try_new(&directives) {
+            Ok(filter) => return filter,
+            Err(e) => eprintln!("Ignoring invalid {ENV_VAR}=\"{directives}\": {e}")
+        }
+    }
+
+    // The level was validated when the config was parsed
+    EnvFilter::try_new(&config.level).unwrap_or_else(|_| EnvFilter::new(LogConfig::default().level))
+}
+
+/// Installs the global logger. The returned guard flushes the log file when dropped, so it has to live until the end of main
+pub fn init(config: &LogConfig) -> Option<WorkerGuard> {
+    let (filter, handle) = reload::Layer::new(filter(config));
+    let _ = FILTER.set(handle);
+
+    let mut guard = None;
+    let file_layer = config.file.then(|| {
+        let dir = state_dir();
+        // The appender prunes old files before it creates the directory, and complains if it's missing
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            eprintln!("Failed to create the log directory {}: {e}", dir.display());
+            return None;
+        }
+
+        let appender = Builder::new()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("rusty-bar")
+            .filename_suffix("log")
+            .max_log_files(config.max_files)
+            .build(&dir);
+
+        match appender {
+            Ok(appender) => {
+                let (writer, worker) = tracing_appender::non_blocking(appender);
+                guard = Some(worker);
+                Some(fmt::layer().with_writer(writer).with_ansi(false))
+            },
+            Err(e) => {
+                eprintln!("Failed to open a log file in {}: {e}", dir.display());
+                None
+            }
+        }
+    }).flatten();
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .init();
+
+    guard
+}
+
+/// Applies the level of a reloaded config. The environment variable still takes precedence
+pub fn reload(config: &LogConfig) {
+    if let Some(handle) = FILTER.get()
+        && let Err(e) = handle.reload(filter(config))
+    {
+        tracing::warn!("Failed to change the log level: {e}");
+    }
+}
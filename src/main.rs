@@ -1,54 +1,99 @@
 use iced::{
-    Alignment, Color, Element, Font, Length, Padding, Renderer, Subscription, Task, border::{self, Radius}, futures::Stream, stream, theme::{
-        self, 
+    Alignment, Color, Element, Font, Length, Subscription, Task, border, theme::{
+        self,
         Theme
     }, widget::{
-            Container, Space, button::Status, container, mouse_area, row, space, svg, text
+            Column, Row, Space, column, container, mouse_area, row, space
     }, window
 };
 
-use iced::widget::button;
-
 use iced_layershell::{
     daemon,
-    reexport::{Anchor, NewLayerShellSettings},
+    actions::ActionCallback,
+    reexport::{KeyboardInteractivity, NewLayerShellSettings, OutputOption},
     settings::{
         LayerShellSettings, Settings, StartMode
     },
     to_layer_message
 };
-use mpris_client_async::{Mpris, Playback, Player, PlayerEvent, properties::PlaybackStatus};
-
-use futures::{SinkExt, pin_mut, stream::StreamExt};
+use iced_wayland_subscriber::{OutputInfo, WaylandEvent};
+use wayland_client::Connection;
 
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{collections::HashMap, path::{Path, PathBuf}, time::{Duration, Instant}};
 use chrono::Local;
+use tracing::{debug, error, info, warn};
 
 // Weather backend
 mod weather;
-use weather::prelude::*;
-use crate::{media_utils::MprisEvent, weather::{CurrentWeather, HourlyWeather}};
+
+// Events of the local calendars
+mod calendar;
 
 // The notification of rusty bar to the user (things like errrors, notices, and other messages)
 mod notification;
 use crate::notification::Notification;
 
+// The notifications saved to disk, reloaded on startup
+mod notification_history;
+
+// The org.freedesktop.Notifications server, for the notifications of desktop apps
+mod notification_server;
+
 // Contains svgs and other small assets
 mod assets;
 use crate::assets::get_svg;
 
 mod windows;
-use windows::weather_window;
 
 mod graph;
 
 mod media_utils;
 
+// The user configuration, read from config.toml
+mod config;
+use crate::config::{BarConfig, Config, ConfigError, MAIN_BAR, OutputBar};
+
+// The light or dark preference of the desktop
+mod appearance;
+use crate::appearance::ColorScheme;
+
+// Control of a running bar through a unix socket
+mod ipc;
+
+// Command line arguments
+mod cli;
+
+// Logging to stderr and the log file
+mod logging;
+use crate::cli::Cli;
+
+// The widgets of the bar
+mod modules;
+use crate::modules::{BarModule, Context, Module, Modules};
+
 #[derive(Debug, Clone)]
 #[non_exhaustive]
 enum WindowType {
-    Main,
-    Weather
+    /// One of the bars configured, by its name, on the output with this name
+    Bar {
+        bar: String,
+        output: String
+    },
+    Weather,
+    Calendar,
+    Notifications,
+    /// The toasts of the notifications, in a corner of the screen
+    Toasts
+}
+
+/// The state of a bar with autohide enabled
+#[derive(Debug, Clone, Copy)]
+struct Autohide {
+    /// Only a sliver of the bar is shown
+    collapsed: bool,
+    hovered: bool,
+    /// When the pointer last left the bar
+    left_at: Instant,
 }
 
 #[to_layer_message(multi)]
@@ -60,96 +105,98 @@ enum Message {
     NewNotif(Notification),
     NotifRetry(Notification),
 
+    WindowClosed(window::Id),
 
-    SecondTrigger,
-
+    /// A new output was connected, or an existing one was reported on startup
+    OutputAdded(OutputInfo),
+    /// The output listener failed, no more bars can be opened
+    OutputListenerStopped(String),
 
+    /// The config file changed on disk and was reparsed
+    ConfigReloaded(Result<Config, ConfigError>),
 
-    //      WEATHER      \\
-    ParseWeather,
+    /// A message of one of the modules
+    Module(modules::Message),
 
-    ParseCurrentWeather,
-    CurrentWeatherParsed(Result<CurrentWeather, ParsingError>),
+    /// A command received on the ipc socket
+    Ipc(ipc::Request),
 
-    ParseHourlyWeather,
-    HourlyWeatherParsed(Result<Vec<HourlyWeather>, ParsingError>),
+    /// The desktop switched between light and dark
+    ColorSchemeChanged(ColorScheme),
 
-    WeatherWindowMessage(weather_window::Message),
-    WeatherWindowToggle,
+    /// The pointer entered an autohiding bar
+    BarEntered(window::Id),
+    /// The pointer left an autohiding bar
+    BarLeft(window::Id),
+    /// The autohide delay passed since the pointer left the bar
+    AutohideTimeout(window::Id),
 
+    /// Opens or closes a popup, next to the bar it was clicked on, or the main bar without one
+    WeatherWindowToggle(Option<window::Id>),
+    CalendarWindowToggle(Option<window::Id>),
+    NotificationWindowToggle(Option<window::Id>),
+}
 
-    //      MPRIS      \\
-    PlayerEvent(MprisEvent),
-
-    /// This runs when a new mpris object has been returned, and replaces the one in the state
-    NewPlayers(Result<Vec<Arc<Player>>, zbus::Error>),
-    TrackedPlayer(Option<Arc<Player>>),
+impl Message {
+    /// Tells the popup toggles which bar they were clicked on
+    fn on_bar(self, id: window::Id) -> Self {
+        use Message::*;
+        match self {
+            WeatherWindowToggle(None) => WeatherWindowToggle(Some(id)),
+            CalendarWindowToggle(None) => CalendarWindowToggle(Some(id)),
+            NotificationWindowToggle(None) => NotificationWindowToggle(Some(id)),
+            message => message
+        }
+    }
 }
 
 #[derive(Default)]
 struct State {
     window_ids: HashMap<window::Id, WindowType>,
 // GLOBAL VARS
-    theme: Option<Theme>,
-    radius: i32,
-    time_fmt: &'static str,
-    spacing: u32,
-    hpadding: u32,
-    notifications: Vec<Notification>,
-// LEFT SIDE
-    clock: String,
-    clock_widget_width: u32,    // TODO: ELLIMINATE!!
-
-    // Parse initial stuff, it will only be run once, when the program starts
-    first_parse: bool,
-
-
-
-    //      WEATHER      \\
-
-    // None if the location should be parsed from ip address, not a specified position
-    tracked_location: Option<Coordinates>,
-    units: Units,
-    weather_current: Option<CurrentWeather>,
-
-    weather_hourly: Vec<HourlyWeather>,
-    weather_hours_to_parse: Option<u8>,
-    
+    /// The context of the main bar, also used by popups
+    context: Context,
+    /// The context of every bar, keyed by the bar's name
+    bar_contexts: HashMap<String, Context>,
+    config: Config,
+    config_path: PathBuf,
+    /// The outputs known, keyed by their name
+    outputs: HashMap<String, OutputInfo>,
+    connection: Option<Connection>,
+
+    modules: Modules,
 
     weather_window_id: Option<window::Id>,
-    weather_window_state: weather_window::State,
-
-
-
-    //      MPRIS      \\
-    // An mpris instance is made in subscription, and every time a player_change event occurs it sends an arc copy.
-    mpris: Option<Arc<Mpris<'static>>>,
-
-    tracked_player: Option<Arc<Player>>,
-    players: Vec<Arc<Player>>,
+    calendar_window_id: Option<window::Id>,
+    notification_window_id: Option<window::Id>,
+    /// The surface of the toasts, open while there are toasts
+    toast_window_id: Option<window::Id>,
+    /// The number of toasts the surface is sized for
+    toasts_shown: usize,
+
+    /// True if the bars were closed with the `hide` command
+    hidden: bool,
+    /// The bars with autohide enabled
+    autohide: HashMap<window::Id, Autohide>,
+
+    color_scheme: ColorScheme,
 }
 
 impl State {
-    fn new(
-        theme: Theme,
-        radius: i32, 
-        spacing: u32, 
-        time_fmt: &'static str, 
-        clock_widget_width: u32,
-        hpadding: u32,
-        units: Units
-    ) -> Self {
-        Self { 
-            theme: Some(theme),
-            radius,
-            time_fmt,
-            spacing,
-            clock_widget_width,
-            hpadding,
-            units,
-            first_parse: true,
-            ..Default::default() 
-        }
+    fn new(config: Config, config_path: PathBuf, connection: Connection) -> (Self, Task<Message>) {
+        let mut modules = Modules::default();
+        let task = modules.configure(&config);
+
+        let mut state = Self {
+            config_path,
+            config,
+            connection: Some(connection),
+            modules,
+            ..Default::default()
+        };
+        state.update_contexts();
+
+        (state, task)
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
@@ -159,379 +206,659 @@ impl State {
 
             RemoveWindow(id) => {
                 self.window_ids.remove(&id);
-                println!("Removing window id {id}");
+                debug!(%id, "Removing window");
                 Task::none()
             },
 
 
             NewNotif(notif) => {
-                println!("New notification: {:#?}", notif);
-                self.notifications.push(notif);
-                Task::none()
-            },
-            NotifRetry(notif) => notif.retry().expect("{0}"),
-            
-
-
-            SecondTrigger => {
-                let now = Local::now();
-                self.clock = now.format(self.time_fmt).to_string();
+                info!(?notif, "New notification");
+                self.modules.notifications.notify_bar(notif);
 
-                if self.first_parse {
-                    self.first_parse = false;
-                    Task::batch(
-                        [
-                            Task::done(Message::ParseCurrentWeather)
-                        ]
-                    )
+                // The list is open, so the new one is read right away
+                let read = if self.notification_window_id.is_some() {
+                    Task::done(modules::notifications::Message::Read.into())
                 } else {
                     Task::none()
-                }
+                };
+                Task::batch([read, self.sync_toasts()])
             },
-            
-
-
-            ParseWeather => Task::batch(vec![Task::done(Message::ParseCurrentWeather), Task::done(Message::ParseHourlyWeather)]),
-
-            ParseCurrentWeather => {
-                println!("Parsing current weather");
-
-                use argument::Current;
-                Task::perform(get_current(
-                    self.tracked_location.clone(), 
-                    self.units.clone(),
-                    vec![
-                        Current::Temperature,
-                        Current::IsDay,
-                        Current::ApparentTemp,
-                        Current::Humidity,
-                        Current::WeatherCode,
-                        Current::WindDirection,
-                        Current::WindSpeed,
-                        Current::Precipitation(argument::PrecipitationType::Combined),
-                        Current::Precipitation(argument::PrecipitationType::Rain),
-                        Current::Precipitation(argument::PrecipitationType::Showers),
-                        Current::Precipitation(argument::PrecipitationType::Snowfall)
-                    ]
-                ), Message::CurrentWeatherParsed)
-            },
-            CurrentWeatherParsed(result) => {
-                match result {
-                    Ok(weather) => {
-                        self.weather_current = Some(weather);
+            NotifRetry(notif) => notif.retry().unwrap_or_else(|e| {
+                warn!("{e}");
+                Task::none()
+            }),
+
+            WindowClosed(id) => {
+                match self.window_ids.remove(&id) {
+                    // The bar was closed by the compositor, so its output is gone
+                    Some(WindowType::Bar { output, .. }) => {
+                        info!(%output, "Output was removed");
+                        self.outputs.remove(&output);
+                        self.autohide.remove(&id);
                         Task::none()
                     },
-                    Err(e) => {
-                        Task::done(
-                            NewNotif(
-                                Notification::new_with_retry(
-                                    notification::Level::Error, 
-                                    e.to_string(), 
-                                    Local::now(),
-                                    &Message::ParseCurrentWeather
-                                )
-                            )
-                        )
-                    }
+                    // Autohiding bars were kept open for the popup
+                    Some(WindowType::Weather) => {
+                        self.weather_window_id = None;
+                        self.schedule_hide_all()
+                    },
+                    Some(WindowType::Calendar) => {
+                        self.calendar_window_id = None;
+                        self.schedule_hide_all()
+                    },
+                    Some(WindowType::Notifications) => {
+                        self.notification_window_id = None;
+                        self.schedule_hide_all()
+                    },
+                    // Opened again by the next toast
+                    Some(WindowType::Toasts) => {
+                        self.toast_window_id = None;
+                        self.toasts_shown = 0;
+                        Task::none()
+                    },
+                    None => Task::none()
                 }
             },
 
-            ParseHourlyWeather => {
-                let hours: u8 = self.weather_hours_to_parse.unwrap_or(24);
-                println!("Parsing {} hours of hourly weather!", hours);
-
-                use argument::Hourly;
-                Task::perform(
-                    get_hourly(
-                        self.tracked_location.clone(), 
-                        self.units.clone(), 
-                        vec![
-                            Hourly::WeatherCode,
-                            Hourly::Temperature,
-                            Hourly::ApparentTemp,
-                            Hourly::IsDay,
-                            Hourly::PrecipitationProbability,
-                            Hourly::WindSpeed,
-                            Hourly::Precipitation(argument::PrecipitationType::Combined),
-                            Hourly::Precipitation(argument::PrecipitationType::Rain),
-                            Hourly::Precipitation(argument::PrecipitationType::Showers),
-                            Hourly::Precipitation(argument::PrecipitationType::Snowfall)
-                        ],
-                        hours
-                    ), 
-                    Message::HourlyWeatherParsed
+            OutputAdded(output) => {
+                info!(output = %output.name, description = %output.description, "New output");
+                self.outputs.insert(output.name.clone(), output.clone());
+
+                self.open_bars(&output)
+            },
+            OutputListenerStopped(e) => {
+                Task::done(
+                    NewNotif(
+                        Notification::new(
+                            notification::Level::Error,
+                            format!("Stopped listening for new outputs: {e}"),
+                            Local::now()
+                        )
+                    )
                 )
             },
-            HourlyWeatherParsed(result) => {
+
+            ConfigReloaded(result) => {
                 match result {
-                    Ok(result) => {
-                        self.weather_hourly = result;
-                        Task::none()
-                    },
+                    Ok(config) => self.apply_config(config),
                     Err(e) => {
                         Task::done(
                             NewNotif(
-                                Notification::new_with_retry(
-                                    notification::Level::Error, 
-                                    e, 
-                                    Local::now(), 
-                                    &ParseHourlyWeather
+                                Notification::new(
+                                    notification::Level::Error,
+                                    format!("Failed to reload the config, keeping the previous one: {e}"),
+                                    Local::now()
                                 )
                             )
                         )
                     }
                 }
-            }
+            },
+
+            Message::Module(message) => {
+                let tick = matches!(message, modules::Message::Clock(modules::clock::Message::Tick));
+                let task = self.modules.update(message);
+
+                // A tick only changes the surfaces showing the time, the others aren't redrawn
+                if tick {
+                    iced_layershell::redraw_only(self.clock_windows());
+                }
+
+                Task::batch([task, self.sync_toasts()])
+            },
+
+            Ipc(request) => self.handle_command(request),
+
+            ColorSchemeChanged(scheme) => {
+                info!(?scheme, "Color scheme changed");
+                self.color_scheme = scheme;
+                self.update_contexts();
+                Task::none()
+            },
+
+            BarEntered(id) => {
+                let Some(autohide) = self.autohide.get_mut(&id) else {
+                    return Task::none();
+                };
+                autohide.hovered = true;
 
+                if autohide.collapsed {
+                    self.expand(id)
+                } else {
+                    Task::none()
+                }
+            },
+            BarLeft(id) => {
+                let Some(autohide) = self.autohide.get_mut(&id) else {
+                    return Task::none();
+                };
+                autohide.hovered = false;
 
-            WeatherWindowToggle => {
+                self.schedule_hide(id)
+            },
+            AutohideTimeout(id) => {
+                let (Some(autohide), Some(bar)) = (self.autohide.get(&id), self.bar_config(id)) else {
+                    return Task::none();
+                };
+
+                // The pointer could have come back and left again since this timeout was scheduled
+                let waited = autohide.left_at.elapsed() >= Duration::from_millis(bar.autohide.delay);
+
+                if waited && !autohide.hovered && !autohide.collapsed && !self.popup_open() {
+                    self.collapse(id)
+                } else {
+                    Task::none()
+                }
+            },
+
+
+            WeatherWindowToggle(bar) => {
                 if let Some(id) = self.weather_window_id {
                     // println!("Closing weather_window with id: {}", id);
                     self.weather_window_id = None;
                     window::close(id)
                 } else {
-                    let id = window::Id::unique();
-                    self.window_ids.insert(id, WindowType::Weather);
+                    let (id, task) = self.open_popup(WindowType::Weather, (450, 400), bar);
                     self.weather_window_id = Some(id);
+                    task
+                }
+            },
+            CalendarWindowToggle(bar) => {
+                if let Some(id) = self.calendar_window_id {
+                    self.calendar_window_id = None;
+                    window::close(id)
+                } else {
+                    let (id, task) = self.open_popup(WindowType::Calendar, windows::calendar_window::size(!self.config.calendar.paths.is_empty()), bar);
+                    self.calendar_window_id = Some(id);
 
-                    // println!("Opening new weather_window with id: {}", id);
-
-                    let mut tasks = Vec::new();
-                    // if self.weather_hourly.is_empty() {
-                    //     println!("Opening weather popup, but hourly_weather is not parsed, requesting parsing!");
-                    //     tasks.push(Task::done(ParseHourlyWeather));
-                    // }
-                    
-                    tasks.push(
-                        Task::done(Message::NewLayerShell { 
-                            settings: NewLayerShellSettings { 
-                                size: Some((450, 400)),
-                                layer: iced_layershell::reexport::Layer::Top,
-                                anchor: Anchor::Top | Anchor::Left,
-                                margin: Some((10, 0, 0, 30)),
-                                keyboard_interactivity: iced_layershell::reexport::KeyboardInteractivity::OnDemand, 
-                                output_option: iced_layershell::reexport::OutputOption::None,
-                                ..Default::default()
-                            },
-                            id
-                        })
-                    );
-
-                    Task::batch(tasks)
+                    // The calendar opens on the current month, wherever it was left
+                    Task::batch([task, Task::done(windows::calendar_window::Message::Today.into())])
                 }
             },
-            WeatherWindowMessage(msg) => self.weather_window_state.update(msg),
+            NotificationWindowToggle(bar) => {
+                if let Some(id) = self.notification_window_id {
+                    self.notification_window_id = None;
+                    window::close(id)
+                } else {
+                    let (id, task) = self.open_popup(WindowType::Notifications, windows::notification_window::SIZE, bar);
+                    self.notification_window_id = Some(id);
 
+                    Task::batch([task, Task::done(modules::notifications::Message::Read.into())])
+                }
+            },
 
+            _ => {Task::none()}
+        }
+    }
 
-            PlayerEvent(event) => {
-                match event {
-                    MprisEvent::StreamEnded => {
-                        self.mpris = None;
-                        self.tracked_player = None;
+    /// Applies a reloaded config to the running bar.
+    /// Appearance is changed in place, bars are opened or closed if they were enabled or disabled on an output,
+    /// and the bar surfaces are reconfigured if the geometry changed
+    fn apply_config(&mut self, config: Config) -> Task<Message> {
+        let mut tasks = vec![self.modules.configure(&config)];
+
+        logging::reload(&config.log);
+        let old = std::mem::replace(&mut self.config, config);
+        self.update_contexts();
+
+        // The bars of both configs, so removed bars are closed
+        let mut names: Vec<String> = old.bar_names().chain(self.config.bar_names()).map(String::from).collect();
+        names.sort();
+        names.dedup();
+
+        let outputs: Vec<OutputInfo> = self.outputs.values().cloned().collect();
+        for (output, name) in outputs.iter().flat_map(|output| names.iter().map(move |name| (output, name))) {
+            let old_bar = old.output_bar(name, &output.name, &output.description);
+            let new_bar = self.config.output_bar(name, &output.name, &output.description);
+
+            match (self.bar_id(name, &output.name), old_bar, new_bar) {
+                (Some(id), _, None) => tasks.push(self.close_bar(id)),
+                (None, _, Some(_)) => tasks.push(self.open_bar(name, output)),
+                // The bar is opened again, so it starts in the right autohide state
+                (Some(id), Some(old), Some(new)) if old.bar.autohide.enabled != new.bar.autohide.enabled => {
+                    tasks.push(self.close_bar(id));
+                    tasks.push(self.open_bar(name, output));
+                },
+                (Some(id), Some(old), Some(new)) => {
+                    let collapsed = self.autohide.get(&id).is_some_and(|autohide| autohide.collapsed);
+                    tasks.extend(Self::reconfigure_bar(id, &old.bar, &new.bar, collapsed));
+                },
+                _ => {}
+            }
+        }
 
-                        println!("MPRIS stream ended");
+        Task::batch(tasks)
+    }
 
-                        Task::none()
-                    },
-                    MprisEvent::NewInstance(instance) => {
-                        println!("New MPRIS instance");
+    /// Runs a command of the ipc socket, and answers it
+    fn handle_command(&mut self, request: ipc::Request) -> Task<Message> {
+        use ipc::{Command, Reply};
 
-                        self.mpris = Some(instance.clone());
-                        Task::perform(async move {instance.get_players().await}, NewPlayers)
-                    },
-                    MprisEvent::Event(event) => {
-                        println!("New event: {:?}", event);
-
-                        use mpris_client_async::PlayerEvent::*;
-                        match event {
-                            Connected(player) => self.players.push(player),
-                            Disconnected(player) => {
-                                // If the player was the tracked, clear it
-                                if let Some(tracked) = self.tracked_player.as_ref() && tracked.dbus_name() == player.dbus_name() {
-                                    self.tracked_player = None;
-                                }
-
-                                self.players.retain(|other| player.dbus_name() != other.dbus_name());
-                            }
-                        };
-
-                        let cloned = self.players.iter().map(|player| player.clone()).collect();
-                        Task::perform(media_utils::get_tracked_player(cloned), TrackedPlayer)
+        debug!(command = %request.command, "Received command");
+
+        let task = match request.command {
+            Command::WeatherRefresh => Task::done(modules::weather::Message::Parse.into()),
+            Command::WeatherToggle => Task::done(Message::WeatherWindowToggle(None)),
+            Command::Reload => {
+                match Config::load(&self.config_path) {
+                    Ok(config) => self.apply_config(config),
+                    Err(e) => {
+                        request.reply.send(Reply::error(e));
+                        return Task::none();
                     }
                 }
             },
-
-            NewPlayers(maybe_players) => {
-                self.tracked_player = None;
-                match maybe_players {
-                    Ok(players) => self.players = players,
-                    Err(e) => eprintln!("get_players on mpris object returned with error: {e}")
+            Command::Hide => self.set_hidden(true),
+            Command::Show => self.set_hidden(false),
+            Command::ToggleVisibility => self.set_hidden(!self.hidden),
+            Command::TimerToggle => Task::done(modules::timer::Message::Toggle.into()),
+            Command::TimerReset => Task::done(modules::timer::Message::Reset.into()),
+            Command::TimerMode => Task::done(modules::timer::Message::NextMode.into()),
+            Command::TimerSkip => Task::done(modules::timer::Message::Skip.into()),
+            Command::DndOn => Task::done(modules::notifications::Message::SetDnd(true).into()),
+            Command::DndOff => Task::done(modules::notifications::Message::SetDnd(false).into()),
+            Command::DndToggle => Task::done(modules::notifications::Message::ToggleDnd.into()),
+            Command::NotificationsList(age) => {
+                if self.config.notifications.history_days == 0 {
+                    request.reply.send(Reply::error("the notification history is turned off"));
+                    return Task::none();
                 }
 
-                let cloned: Vec<Arc<Player>> = self.players.iter().map(|player| player.clone()).collect();
-                Task::perform(media_utils::get_tracked_player(cloned), TrackedPlayer)
+                // The history can be long, it's read on a blocking thread instead of stalling the bar
+                let reply = request.reply;
+                return Task::perform(
+                    async move {
+                        match tokio::task::spawn_blocking(move || notification_history::read(&notification_history::path(), age)).await {
+                            Ok(records) => reply.send(records.map_or_else(Reply::error, Reply::data)),
+                            Err(e) => reply.send(Reply::error(e))
+                        }
+                    },
+                    |_| Message::Nothing
+                );
             },
-            TrackedPlayer(player) => {
-                println!("Currently tracked player is: {}", player.as_ref().unwrap().dbus_name());
-                self.tracked_player = player;
+        };
 
-                Task::none()
-            }
+        request.reply.send(Reply::ok());
+        task
+    }
 
-            _ => {Task::none()}
+    /// Closes every bar, or opens them again on every output
+    fn set_hidden(&mut self, hidden: bool) -> Task<Message> {
+        if self.hidden == hidden {
+            return Task::none();
+        }
+        self.hidden = hidden;
+
+        if hidden {
+            let ids: Vec<window::Id> = self.window_ids
+                .iter()
+                .filter(|(_, window)| matches!(window, WindowType::Bar { .. }))
+                .map(|(id, _)| *id)
+                .collect();
+
+            Task::batch(ids.into_iter().map(|id| self.close_bar(id)).collect::<Vec<_>>())
+        } else {
+            let outputs: Vec<OutputInfo> = self.outputs.values().cloned().collect();
+            Task::batch(outputs.iter().map(|output| self.open_bars(output)).collect::<Vec<_>>())
         }
     }
 
-    fn separator<'a>() -> Container<'a, Message, Theme, Renderer> {
-        container(Space::new())
-            .width(2)
-            .height(30)
-            .align_y(Alignment::Center)
-            .align_x(Alignment::Center)
-            .style(|theme: &Theme| {
-                let palette = theme.extended_palette();
+    /// Opens every bar of the output that isn't open yet
+    fn open_bars(&mut self, output: &OutputInfo) -> Task<Message> {
+        let names: Vec<String> = self.config.bar_names().map(String::from).collect();
 
-                container::Style::default()
-                    .background(palette.background.weakest.color)
-            })
+        Task::batch(
+            names.iter()
+                .filter(|name| self.bar_id(name, &output.name).is_none())
+                .map(|name| self.open_bar(name, output))
+                .collect::<Vec<_>>()
+        )
     }
 
-    /// Returns the type of the window, if it's not found in `self.window_ids` it's assumed to be Main
-    fn match_id(&self, id: &window::Id) -> &WindowType {
-        match self.window_ids.get(&id) {
-            Some(window) => window,
-            None => &WindowType::Main
+    /// Opens a bar on the output, unless it's not shown there in the config or the bars are hidden
+    fn open_bar(&mut self, name: &str, output: &OutputInfo) -> Task<Message> {
+        if self.hidden {
+            return Task::none();
         }
+
+        let Some(OutputBar { bar, .. }) = self.config.output_bar(name, &output.name, &output.description) else {
+            info!(bar = name, output = %output.name, "The bar is not shown on this output");
+            return Task::none();
+        };
+
+        let id = window::Id::unique();
+        self.window_ids.insert(id, WindowType::Bar { bar: name.to_string(), output: output.name.clone() });
+
+        // Autohiding bars are shown on startup, and hidden if the pointer doesn't enter them
+        let hide = if bar.autohide.enabled {
+            self.autohide.insert(id, Autohide { collapsed: false, hovered: false, left_at: Instant::now() });
+            self.schedule_hide(id)
+        } else {
+            Task::none()
+        };
+
+        let open = Task::done(Message::NewLayerShell {
+            settings: NewLayerShellSettings {
+                size: Some(bar.surface_size()),
+                layer: bar.layer,
+                anchor: bar.position.anchor(),
+                exclusive_zone: Some(bar.exclusive_zone()),
+                margin: Some(bar.margin),
+                keyboard_interactivity: KeyboardInteractivity::OnDemand,
+                output_option: OutputOption::Output(output.wl_output.clone()),
+                // Lets compositor rules tell the bars apart
+                namespace: Some(format!("rusty-bar-{name}")),
+                ..Default::default()
+            },
+            id
+        });
+
+        Task::batch([open, hide])
     }
 
-    fn view(&self, id: window::Id) -> Element<'_, Message> {
-        use WindowType::*;
-        match *self.match_id(&id) {
-            Main => self.main_view(),
-            Weather => self.weather_window_state.view(&self),
-        }
+    /// Opens a popup next to a bar, on its output. Next to the main bar on the focused output without one
+    fn open_popup(&mut self, window: WindowType, size: (u32, u32), bar: Option<window::Id>) -> (window::Id, Task<Message>) {
+        let position = bar.and_then(|bar| self.bar_config(bar)).map_or(self.config.bar.position, |bar| bar.position);
+        let output_option = match bar.and_then(|bar| self.window_ids.get(&bar)) {
+            Some(WindowType::Bar { output, .. }) => self.outputs
+                .get(output)
+                .map_or(OutputOption::None, |output| OutputOption::Output(output.wl_output.clone())),
+            _ => OutputOption::None
+        };
+
+        let id = window::Id::unique();
+        self.window_ids.insert(id, window);
+
+        let task = Task::done(Message::NewLayerShell {
+            settings: NewLayerShellSettings {
+                size: Some(size),
+                layer: iced_layershell::reexport::Layer::Top,
+                anchor: position.popup_anchor(),
+                margin: Some(position.popup_margin()),
+                keyboard_interactivity: KeyboardInteractivity::OnDemand,
+                output_option,
+                ..Default::default()
+            },
+            id
+        });
+
+        (id, task)
     }
 
-    fn main_view(&self) -> Element<'_, Message> {
-        let clock = { container(
-            text(&self.clock)
-            .size(36)
-            .center()
-            .width(self.clock_widget_width)
-            .style(text::primary)
-        )
-        .padding(Padding::default().horizontal(self.hpadding))
-        .width(Length::Shrink)
-        .height(Length::Fill)
-        .style(|theme: &Theme| {
-            let palette = theme.extended_palette();
+    /// Opens, resizes or closes the surface of the toasts, to fit the toasts shown
+    fn sync_toasts(&mut self) -> Task<Message> {
+        let shown = self.modules.notifications.toasts_shown();
+        if shown == self.toasts_shown {
+            return Task::none();
+        }
+        self.toasts_shown = shown;
 
-            container::Style::default()
-                .background(palette.background.weak.color)
-                .border(border::rounded(self.radius))
-        })
-        };
-        
-        let weather_widget: Element<'_, Message> = {
-            match &self.weather_current {
-                Some(weather) => {
-                    let svg_handle = svg::Handle::from_memory(
-                        get_svg(
-                            if weather.is_day.unwrap() {"day"} else {"night"},
-                            weather.code.as_ref().unwrap().get_svg_name().as_str()
-                        ).as_bytes()
-                    );
-
-                    container
-                    (
-                        mouse_area
-                        (
-                            row!
-                            [
-                                svg(svg_handle)
-                                    .width(36)
-                                    .height(36)
-                                    .content_fit(iced::ContentFit::Fill),
-                                text(weather.temperature.as_ref().unwrap().stringify())
-                                    .align_y(Alignment::Center)
-                                    .size(36)
-                                    .style(text::primary)
-                            ]
-                            .spacing(5)
-                            .align_y(Alignment::Center)
-                        )
-                        .on_press(Message::WeatherWindowToggle)
-                    )
-                    .padding(Padding::default().horizontal(self.hpadding))
-                    .width(Length::Shrink)
-                    .height(Length::Fill)
-                    .style(|theme: &Theme| {
-                        let palette = theme.extended_palette();
-
-                        container::Style::default()
-                            .background(palette.background.weak.color)
-                            .border(border::rounded(self.radius))
-                    })
-                    .into()
-                },
-                None => {
-                    button
-                    (
-                            svg(
-                                svg::Handle::from_memory(get_svg("commons", "question_mark").as_bytes())
-                            )
-                            .width(36)
-                            .height(36)
-                    )
-                    .on_press(Message::ParseCurrentWeather)
-                    .style(|theme: &Theme, state: Status| button::Style {
-                        background: Some(if state == Status::Hovered {theme.extended_palette().background.stronger.color} else {theme.extended_palette().background.weak.color}.into()),
-                        border: iced::Border { radius: Radius::new(self.radius as f32), ..Default::default() },
+        match (self.toast_window_id, shown) {
+            (Some(id), 0) => {
+                self.toast_window_id = None;
+                self.window_ids.remove(&id);
+                window::close(id)
+            },
+            (Some(id), shown) => Task::done(Message::SizeChange { id, size: windows::toast_window::size(shown) }),
+            (None, 0) => Task::none(),
+            (None, shown) => {
+                let id = window::Id::unique();
+                self.window_ids.insert(id, WindowType::Toasts);
+                self.toast_window_id = Some(id);
+
+                Task::done(Message::NewLayerShell {
+                    settings: NewLayerShellSettings {
+                        size: Some(windows::toast_window::size(shown)),
+                        layer: iced_layershell::reexport::Layer::Top,
+                        anchor: self.config.bar.position.toast_anchor(),
+                        margin: Some((10, 10, 10, 10)),
+                        // Toasts never take the focus from the app being used
+                        keyboard_interactivity: KeyboardInteractivity::None,
+                        output_option: OutputOption::None,
+                        namespace: Some(String::from("rusty-bar-toasts")),
                         ..Default::default()
-                    })
-                    .padding(Padding::default().horizontal(self.hpadding))
-                    .width(Length::Shrink)
-                    .height(Length::Fill)
-                    .into()
-                }
+                    },
+                    id
+                })
             }
+        }
+    }
+
+    /// Autohiding bars stay shown while a popup is open
+    fn popup_open(&self) -> bool {
+        self.weather_window_id.is_some() || self.calendar_window_id.is_some() || self.notification_window_id.is_some()
+    }
+
+    /// Closes a bar, without treating it as its output being removed
+    fn close_bar(&mut self, id: window::Id) -> Task<Message> {
+        self.window_ids.remove(&id);
+        self.autohide.remove(&id);
+        window::close(id)
+    }
+
+    /// The layer shell changes needed to turn the `old` bar into the `new` one.
+    /// Collapsed bars keep their sliver, the new size and margin are used when they expand
+    fn reconfigure_bar(id: window::Id, old: &BarConfig, new: &BarConfig, collapsed: bool) -> Vec<Task<Message>> {
+        let mut tasks = Vec::new();
+
+        let size = |bar: &BarConfig| if collapsed { bar.collapsed_size() } else { bar.surface_size() };
+        let margin = |bar: &BarConfig| if collapsed { bar.collapsed_margin() } else { bar.margin };
+
+        if size(old) != size(new) {
+            tasks.push(Task::done(Message::SizeChange { id, size: size(new) }));
+        }
+        if margin(old) != margin(new) {
+            tasks.push(Task::done(Message::MarginChange { id, margin: margin(new) }));
+        }
+        if old.position != new.position {
+            tasks.push(Task::done(Message::AnchorChange { id, anchor: new.position.anchor() }));
+        }
+        if old.layer != new.layer {
+            tasks.push(Task::done(Message::LayerChange { id, layer: new.layer }));
+        }
+        if old.exclusive_zone() != new.exclusive_zone() {
+            tasks.push(Task::done(Message::ExclusiveZoneChange { id, zone_size: new.exclusive_zone() }));
+        }
+
+        tasks
+    }
+
+    /// The bar config of a bar window, with the overrides of its output applied
+    fn bar_config(&self, id: window::Id) -> Option<BarConfig> {
+        let Some(WindowType::Bar { bar, output }) = self.window_ids.get(&id) else {
+            return None;
+        };
+
+        self.config.output_bar(bar, output, self.output_description(output)).map(|output_bar| output_bar.bar)
+    }
+
+    fn output_description(&self, output: &str) -> &str {
+        self.outputs.get(output).map(|output| output.description.as_str()).unwrap_or_default()
+    }
+
+    /// Rebuilds the contexts of the bars, after the config or the color scheme changed
+    fn update_contexts(&mut self) {
+        self.context = Context::for_bar(&self.config, self.color_scheme, MAIN_BAR);
+        self.bar_contexts = self.config
+            .bar_names()
+            .map(|name| (name.to_string(), Context::for_bar(&self.config, self.color_scheme, name)))
+            .collect();
+    }
+
+    /// Hides an autohiding bar after its delay, unless the pointer comes back or a popup is open
+    fn schedule_hide(&mut self, id: window::Id) -> Task<Message> {
+        let Some(bar) = self.bar_config(id) else {
+            return Task::none();
+        };
+        let Some(autohide) = self.autohide.get_mut(&id) else {
+            return Task::none();
         };
+        autohide.left_at = Instant::now();
 
-        let left = row![
-            clock,
-            Self::separator(),
-            weather_widget,
-            Self::separator(),
-        ]
-            .align_y(Alignment::Center)
-            .spacing(self.spacing);
+        Task::perform(tokio::time::sleep(Duration::from_millis(bar.autohide.delay)), move |_| Message::AutohideTimeout(id))
+    }
+
+    /// Schedules hiding every autohiding bar the pointer isn't on
+    fn schedule_hide_all(&mut self) -> Task<Message> {
+        let ids: Vec<window::Id> = self.autohide
+            .iter()
+            .filter(|(_, autohide)| !autohide.hovered && !autohide.collapsed)
+            .map(|(id, _)| *id)
+            .collect();
+
+        Task::batch(ids.into_iter().map(|id| self.schedule_hide(id)).collect::<Vec<_>>())
+    }
+
+    /// Shrinks an autohiding bar to a sliver on the screen edge
+    fn collapse(&mut self, id: window::Id) -> Task<Message> {
+        let Some(bar) = self.bar_config(id) else {
+            return Task::none();
+        };
+        let Some(autohide) = self.autohide.get_mut(&id) else {
+            return Task::none();
+        };
+        autohide.collapsed = true;
+
+        Task::batch([
+            Task::done(Message::ExclusiveZoneChange { id, zone_size: 0 }),
+            Task::done(Message::MarginChange { id, margin: bar.collapsed_margin() }),
+            Task::done(Message::SizeChange { id, size: bar.collapsed_size() }),
+            Self::whole_input_region(id)
+        ])
+    }
 
+    /// Brings an autohiding bar back to its full size
+    fn expand(&mut self, id: window::Id) -> Task<Message> {
+        let Some(bar) = self.bar_config(id) else {
+            return Task::none();
+        };
+        let Some(autohide) = self.autohide.get_mut(&id) else {
+            return Task::none();
+        };
+        autohide.collapsed = false;
+
+        Task::batch([
+            Task::done(Message::MarginChange { id, margin: bar.margin }),
+            Task::done(Message::SizeChange { id, size: bar.surface_size() }),
+            Self::whole_input_region(id)
+        ])
+    }
+
+    /// Makes the whole surface accept the pointer, the region is clipped to the surface by the compositor.
+    /// Otherwise the region of the previous size would be kept after a resize
+    fn whole_input_region(id: window::Id) -> Task<Message> {
+        Task::done(Message::SetInputRegion {
+            id,
+            callback: ActionCallback::new(|region| region.add(0, 0, i32::MAX, i32::MAX))
+        })
+    }
 
-//
-        let middle = row![]
-            .align_y(Alignment::Center)
-            .spacing(self.spacing);
-        
+    /// The bars showing a clock, and the calendar popup
+    fn clock_windows(&self) -> Vec<window::Id> {
+        self.window_ids
+            .iter()
+            .filter(|(id, window)| match window {
+                WindowType::Bar { bar, output } => {
+                    // A collapsed bar is an empty sliver
+                    !self.autohide.get(id).is_some_and(|autohide| autohide.collapsed)
+                        && self.config.output_layout(bar, output, self.output_description(output)).contains(Module::Clock)
+                },
+                WindowType::Calendar => true,
+                _ => false
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
 
+    /// The id of a bar on an output
+    fn bar_id(&self, name: &str, output_name: &str) -> Option<window::Id> {
+        self.window_ids
+            .iter()
+            .find(|(_, window)| matches!(window, WindowType::Bar { bar, output } if bar == name && output == output_name))
+            .map(|(id, _)| *id)
+    }
 
-//
-        let right = row![]
-            .align_y(Alignment::Center)
-            .spacing(self.spacing);
+    fn view(&self, id: window::Id) -> Element<'_, Message> {
+        use WindowType::*;
+        match self.window_ids.get(&id) {
+            Some(Bar { bar, output }) => match self.autohide.get(&id) {
+                // The sliver is empty, it only waits for the pointer
+                Some(autohide) if autohide.collapsed => {
+                    mouse_area(Space::new().width(Length::Fill).height(Length::Fill))
+                        .on_enter(Message::BarEntered(id))
+                        .into()
+                },
+                Some(_) => {
+                    mouse_area(self.bar_view(id, bar, output))
+                        .on_enter(Message::BarEntered(id))
+                        .on_exit(Message::BarLeft(id))
+                        .into()
+                },
+                None => self.bar_view(id, bar, output)
+            },
+            Some(Weather) => self.modules.weather
+                .popup_view(&self.context)
+                .unwrap_or_else(|| Space::new().into()),
+            Some(Calendar) => self.modules.clock
+                .popup_view(&self.context)
+                .unwrap_or_else(|| Space::new().into()),
+            Some(Notifications) => self.modules.notifications
+                .popup_view(&self.context)
+                .unwrap_or_else(|| Space::new().into()),
+            Some(Toasts) => self.modules.notifications.toasts_view(&self.context),
+            None => Space::new().into()
+        }
+    }
 
+    /// A row of modules, or a column on vertical bars, in the order they are listed in the config
+    fn section<'a>(&'a self, ctx: &'a Context, modules: &[Module]) -> Element<'a, Message> {
+        let children = modules.iter().map(|module| self.modules.view(*module, ctx));
+
+        if ctx.is_vertical() {
+            Column::with_children(children)
+                .align_x(Alignment::Center)
+                .spacing(ctx.spacing)
+                .into()
+        } else {
+            Row::with_children(children)
+                .align_y(Alignment::Center)
+                .spacing(ctx.spacing)
+                .into()
+        }
+    }
 
-        container(
+    fn bar_view<'a>(&'a self, id: window::Id, bar: &str, output: &str) -> Element<'a, Message> {
+        let ctx = self.bar_contexts.get(bar).unwrap_or(&self.context);
+        let layout = self.config.output_layout(bar, output, self.output_description(output));
+
+        let content: Element<'_, Message> = if ctx.is_vertical() {
+            column![
+                self.section(ctx, &layout.left),
+                space::vertical(),
+                self.section(ctx, &layout.middle),
+                space::vertical(),
+                self.section(ctx, &layout.right)
+            ]
+                .align_x(Alignment::Center)
+                .padding(5)
+                .into()
+        } else {
             row![
-                left,
+                self.section(ctx, &layout.left),
                 space::horizontal(),
-                middle,
+                self.section(ctx, &layout.middle),
                 space::horizontal(),
-                right
+                self.section(ctx, &layout.right)
             ]
                 .align_y(Alignment::Center)
                 .padding(5)
-        )
+                .into()
+        };
+
+        // The popups open next to the bar they were toggled on
+        let content = content.map(move |message: Message| message.on_bar(id));
+
+        container(content)
         .width(Length::Fill)
         .height(Length::Fill)
         .align_y(Alignment::Center)
@@ -541,18 +868,32 @@ impl State {
 
             container::Style::default()
                 .background(palette.background.base.color)
-                .border(border::rounded(self.radius))
+                .border(border::rounded(ctx.radius))
         })
         .into()
     }
 
-    fn subscription(_state: &State) -> Subscription<Message> {
-        let subs = vec![
-            iced::time::every(Duration::from_secs(1)).map(|_| Message::SecondTrigger),
-            iced::time::every(Duration::from_mins(15)).map(|_| Message::ParseCurrentWeather),
-            iced::Subscription::run(media_utils::mpris_subscription).map(Message::PlayerEvent)
+    fn subscription(state: &State) -> Subscription<Message> {
+        let mut subs = vec![
+            window::close_events().map(Message::WindowClosed),
+            Subscription::run_with(state.config_path.clone(), config::watch).map(Message::ConfigReloaded),
+            state.modules.subscription(),
+            Subscription::run(ipc::listen).map(Message::Ipc)
         ];
 
+        if state.config.follows_color_scheme() {
+            subs.push(Subscription::run(appearance::color_scheme_subscription).map(Message::ColorSchemeChanged));
+        }
+
+        if let Some(connection) = &state.connection {
+            subs.push(
+                iced_wayland_subscriber::listen(connection.clone()).map(|event| match event {
+                    WaylandEvent::OutputInsert(output) => Message::OutputAdded(output),
+                    WaylandEvent::Stop(e) => Message::OutputListenerStopped(e.to_string())
+                })
+            );
+        }
+
         Subscription::batch(subs)
     }
 
@@ -565,6 +906,28 @@ impl State {
 }
 
 fn main() -> iced_layershell::Result {
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{e}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let config_path = match cli {
+        Cli::Run { config } => config.unwrap_or_else(Config::default_path),
+        Cli::Msg { command } => send_command(&command),
+        Cli::CheckConfig { config } => check_config(&config.unwrap_or_else(Config::default_path)),
+        Cli::PrintDefaultConfig => {
+            print!("{}", config::DEFAULT_CONFIG);
+            return Ok(());
+        },
+        Cli::Help => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+    };
+
     assets::load_assets();
 
     // Setting ICED_BACKEND to software will panic, for some reason...
@@ -572,42 +935,40 @@ fn main() -> iced_layershell::Result {
         std::env::set_var("ICED_BACKEND", "tiny-skia");
     }
 
-    let theme = Theme::CatppuccinMocha;
-    let radius = 10;
-    let time_fmt = "%H:%M:%S";
-    let spacing = 4;
-    let clock_widget_width = 140;
-    let hpadding = 4;
-    let units = Units::default();
-    // let units = Units::new(Speed::Mph, TempUnit::Fahrenheit, weather::prelude::Length::Inch);
+    let config = match Config::load(&config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            // The logger is configured by the config, so it's not running yet
+            eprintln!("Invalid configuration: {e}");
+            std::process::exit(1);
+        }
+    };
+    // Flushes the log file when main returns
+    let _log_guard = logging::init(&config.log);
+
+    // Shared with the output listener, so the bars can be opened on the outputs it reports
+    let connection = match Connection::connect_to_env() {
+        Ok(connection) => connection,
+        Err(e) => {
+            error!("Failed to connect to the wayland compositor: {e}");
+            std::process::exit(1);
+        }
+    };
+    let layershell_connection = connection.clone();
 
     daemon(
-        move || {
-            State::new(
-                theme.clone(), 
-                radius, 
-                spacing, 
-                time_fmt, 
-                clock_widget_width,
-                hpadding,
-                units.clone()
-            )
-        },
+        move || State::new(config.clone(), config_path.clone(), connection.clone()),
         "Rusty Bar",
         State::update,
         State::view
     )
     .settings(Settings {
+        // The bars are opened one by one when the outputs are reported
         layer_settings: LayerShellSettings {
-            layer: iced_layershell::reexport::Layer::Top,
-            size: Some((0, 50)),
-            start_mode: StartMode::AllScreens,
-            anchor: Anchor::Top | Anchor::Left | Anchor::Right,
-            exclusive_zone: 50,
-            margin: (5, 10, 5, 10),
-            keyboard_interactivity: iced_layershell::reexport::KeyboardInteractivity::OnDemand,
+            start_mode: StartMode::Background,
             ..Default::default()
         },
+        with_connection: Some(layershell_connection),
         fonts: vec![
             std::include_bytes!("assets/fonts/Itim-Regular.ttf").into()
         ],
@@ -616,8 +977,41 @@ fn main() -> iced_layershell::Result {
     })
     .style(State::style)
     .theme(|state: &State, _| {
-        state.theme.clone()
+        state.context.theme.clone()
     })
     .subscription(State::subscription)
     .run()
 }
+
+/// `rusty_bar msg`, prints the reply of the running bar as JSON and exits with 1 if the command failed
+fn send_command(command: &str) -> ! {
+    match ipc::send(command) {
+        Ok(reply) => {
+            println!("{}", serde_json::to_string(&reply).unwrap_or_default());
+            std::process::exit(if reply.ok { 0 } else { 1 });
+        },
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `rusty_bar check-config`, validates the config without connecting to the compositor
+fn check_config(path: &Path) -> ! {
+    if !path.exists() {
+        println!("{} doesn't exist, the default config will be used", path.display());
+        std::process::exit(0);
+    }
+
+    match Config::load(path) {
+        Ok(_) => {
+            println!("{} is valid", path.display());
+            std::process::exit(0);
+        },
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,8 +1,10 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use futures::{SinkExt, Stream, StreamExt, pin_mut};
+use futures::{SinkExt, Stream, StreamExt, pin_mut, stream::BoxStream};
 use iced::stream;
 use mpris_client_async::{Mpris, Playback, Player, PlayerEvent, properties::PlaybackStatus};
+use tracing::{debug, error};
+use zbus::{fdo::{DBusProxy, PropertiesProxy}, names::InterfaceName};
 
 #[derive(Debug, Clone)]
 pub enum MprisEvent {
@@ -19,7 +21,7 @@ pub fn mpris_subscription() -> impl Stream<Item = MprisEvent> {
         let mpris = Arc::new(match Mpris::new().await {
             Ok(m) => m,
             Err(e) => {
-                eprintln!("Failed to initialise MPRIS listener: {e}");
+                error!("Failed to initialise MPRIS listener: {e}");
                 return; // Stream ends, iced will attempt to restart it
             }
         });
@@ -27,7 +29,7 @@ pub fn mpris_subscription() -> impl Stream<Item = MprisEvent> {
         let stream = match mpris.player_stream().await {
             Ok(s) => s,
             Err(e) => {
-                eprintln!("Failed to subscribe to MPRIS proxy events: {e}");
+                error!("Failed to subscribe to MPRIS proxy events: {e}");
                 return; // Stream ends again, iced will attempt to restart it
             }
         };
@@ -49,4 +51,117 @@ pub async fn get_tracked_player(players: Vec<Arc<Player>>) -> Option<Arc<Player>
     }
 
     None
-}
\ No newline at end of file
+}
+
+/// How often the players are asked if they're fullscreen while one is playing, the property isn't signaled by every player
+const FULLSCREEN_POLL: Duration = Duration::from_secs(5);
+
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// Whether any player on the bus is fullscreen or playing
+#[derive(Debug, Default, Clone, Copy)]
+struct PlayersState {
+    /// Only players with the optional Fullscreen property can be fullscreen
+    fullscreen: bool,
+    playing: bool,
+}
+
+async fn players_state(connection: &zbus::Connection) -> zbus::Result<PlayersState> {
+    let names = DBusProxy::new(connection).await?.list_names().await?;
+    let mut state = PlayersState::default();
+
+    for name in names.iter().filter(|name| name.as_str().starts_with("org.mpris.MediaPlayer2.")) {
+        let properties = PropertiesProxy::builder(connection)
+            .destination(name.as_str())?
+            .path(MPRIS_PATH)?
+            .build()
+            .await?;
+
+        let fullscreen = properties
+            .get(InterfaceName::from_static_str_unchecked("org.mpris.MediaPlayer2"), "Fullscreen")
+            .await
+            .ok()
+            .and_then(|value| bool::try_from(value).ok());
+        let status = properties
+            .get(InterfaceName::from_static_str_unchecked("org.mpris.MediaPlayer2.Player"), "PlaybackStatus")
+            .await
+            .ok()
+            .and_then(|value| String::try_from(value).ok());
+
+        state.fullscreen |= fullscreen == Some(true);
+        state.playing |= status.as_deref() == Some("Playing");
+    }
+
+    Ok(state)
+}
+
+/// Changes of the properties of the players, and players appearing or leaving the bus
+async fn player_changes(connection: &zbus::Connection) -> zbus::Result<BoxStream<'static, ()>> {
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path(MPRIS_PATH)?
+        .build();
+    let properties = zbus::MessageStream::for_match_rule(rule, connection, None).await?.map(|_| ());
+
+    let owners = DBusProxy::new(connection)
+        .await?
+        .receive_name_owner_changed()
+        .await?
+        .filter(|signal| std::future::ready(signal
+            .args()
+            .is_ok_and(|args| args.name().as_str().starts_with("org.mpris.MediaPlayer2."))
+        ))
+        .map(|_| ());
+
+    Ok(futures::stream::select(properties, owners).boxed())
+}
+
+/// Yields whether any player is fullscreen, every time it changes.
+/// The players are checked again when they signal a change, and polled only while one is playing
+pub fn fullscreen_subscription() -> impl Stream<Item = bool> {
+    stream::channel(4, async |mut output| {
+        let connection = match zbus::Connection::session().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to connect to the session bus for fullscreen players: {e}");
+                return;
+            }
+        };
+
+        let mut changes = match player_changes(&connection).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                error!("Failed to listen to the players for fullscreen: {e}");
+                return;
+            }
+        };
+
+        let mut last = None;
+        loop {
+            let state = players_state(&connection).await.unwrap_or_else(|e| {
+                debug!("Failed to check for fullscreen players: {e}");
+                PlayersState::default()
+            });
+
+            if last != Some(state.fullscreen) {
+                last = Some(state.fullscreen);
+                let _ = output.send(state.fullscreen).await;
+            }
+
+            let changed = if state.playing {
+                tokio::select! {
+                    changed = changes.next() => changed,
+                    _ = tokio::time::sleep(FULLSCREEN_POLL) => Some(())
+                }
+            } else {
+                changes.next().await
+            };
+
+            if changed.is_none() {
+                break;
+            }
+        }
+    })
+}
@@ -0,0 +1,582 @@
+use std::{fmt::Display, io, path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Local, Offset, TimeDelta, TimeZone, Timelike, Utc};
+use futures::{SinkExt, Stream};
+use iced::{
+    Alignment, Element, Length, Padding, Subscription, Task, Theme, border,
+    mouse::ScrollDelta,
+    stream,
+    widget::{Column, Row, column, container, mouse_area, text, text::LineHeight}
+};
+use tracing::{debug, warn};
+
+use super::{BarModule, Context, Scroll};
+use crate::{
+    calendar::{self, CalendarError, Event, Occurrence},
+    config::{ClockZone, Config},
+    notification::{self, Notification},
+    windows::calendar_window
+};
+
+/// How often the calendars are read again, vdirsyncer can change them any time
+const CALENDAR_RELOAD: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Tick,
+    /// Goes to the previous or next time zone
+    Scrolled(ScrollDelta),
+    Entered,
+    Exited,
+
+    /// Reads the calendars again
+    LoadEvents,
+    EventsLoaded(Vec<Event>, Vec<CalendarError>),
+
+    Calendar(calendar_window::Message),
+}
+
+impl From<calendar_window::Message> for crate::Message {
+    fn from(message: calendar_window::Message) -> Self {
+        Message::Calendar(message).into()
+    }
+}
+
+/// How often the text of the clock changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+enum Resolution {
+    Second,
+    /// Formats without seconds are still updated every minute, it's cheap and covers the ones with hours and dates
+    #[default]
+    Minute,
+}
+
+impl Resolution {
+    /// The finest resolution of the formats, sub second formats are only updated every second
+    fn of<'a>(formats: impl IntoIterator<Item = &'a str>) -> Self {
+        use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+
+        let seconds = formats.into_iter().flat_map(StrftimeItems::new).any(|item| matches!(
+            item,
+            Item::Numeric(Numeric::Second | Numeric::Nanosecond | Numeric::Timestamp, _)
+                | Item::Fixed(
+                    Fixed::Nanosecond | Fixed::Nanosecond3 | Fixed::Nanosecond6 | Fixed::Nanosecond9
+                        | Fixed::RFC2822 | Fixed::RFC3339 | Fixed::Internal(_)
+                )
+        ));
+
+        if seconds { Resolution::Second } else { Resolution::Minute }
+    }
+
+    /// The time until the text changes next, at the start of the next second or minute.
+    /// A few milliseconds late, so waking up a bit early doesn't format the old time again
+    fn until_next(self, now: DateTime<Utc>) -> Duration {
+        let millis = u64::from(now.timestamp_subsec_millis().min(999));
+        let into_period = match self {
+            Resolution::Second => millis,
+            Resolution::Minute => u64::from(now.second()) * 1000 + millis,
+        };
+        let period = match self {
+            Resolution::Second => 1000,
+            Resolution::Minute => 60_000,
+        };
+
+        Duration::from_millis(period - into_period + 5)
+    }
+
+    /// The wall clock time the text changes next
+    fn next(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + self.until_next(now)
+    }
+
+    /// What the text of the clock depends on, the second or minute of the time and the UTC offset.
+    /// The text changed when this did
+    fn mark<T: TimeZone>(self, now: &DateTime<T>) -> (i64, i32) {
+        let period = match self {
+            Resolution::Second => 1,
+            Resolution::Minute => 60,
+        };
+
+        (now.timestamp().div_euclid(period), now.offset().fix().local_minus_utc())
+    }
+}
+
+/// Sleeps until the wall clock reaches `time`, or until the wall clock is set, like by NTP or by hand.
+/// Unlike a tokio sleep the timer counts the time suspended, so it fires right after a resume past `time`
+async fn sleep_until(time: DateTime<Utc>) -> io::Result<()> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    use tokio::io::unix::AsyncFd;
+
+    // SAFETY: timerfd_create has no preconditions, the fd is owned from here on
+    let fd = unsafe { libc::timerfd_create(libc::CLOCK_REALTIME, libc::TFD_NONBLOCK | libc::TFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let spec = libc::itimerspec {
+        it_interval: libc::timespec { tv_sec: 0, tv_nsec: 0 },
+        it_value: libc::timespec {
+            tv_sec: time.timestamp() as libc::time_t,
+            tv_nsec: time.timestamp_subsec_nanos() as libc::c_long
+        },
+    };
+    let flags = libc::TFD_TIMER_ABSTIME | libc::TFD_TIMER_CANCEL_ON_SET;
+    // SAFETY: the fd is a timerfd and spec lives through the call
+    if unsafe { libc::timerfd_settime(fd.as_raw_fd(), flags, &spec, std::ptr::null_mut()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let fd = AsyncFd::new(fd)?;
+    loop {
+        let mut guard = fd.readable().await?;
+        let read = guard.try_io(|fd| {
+            let mut expirations = [0u8; 8];
+            // SAFETY: the buffer is 8 bytes long, the size of the expiration count
+            match unsafe { libc::read(fd.as_raw_fd(), expirations.as_mut_ptr().cast(), expirations.len()) } {
+                n if n < 0 => Err(io::Error::last_os_error()),
+                _ => Ok(())
+            }
+        });
+
+        match read {
+            Ok(Ok(())) => return Ok(()),
+            // The wall clock was set, the caller looks at it again
+            Ok(Err(e)) if e.raw_os_error() == Some(libc::ECANCELED) => return Ok(()),
+            Ok(Err(e)) => return Err(e),
+            Err(_would_block) => continue
+        }
+    }
+}
+
+/// Ticks every time the text of the clock changes, instead of every second
+fn ticks(resolution: &Resolution) -> impl Stream<Item = ()> + use<> {
+    let resolution = *resolution;
+
+    stream::channel(1, async move |mut output| {
+        let mut last = resolution.mark(&Local::now());
+        let mut timer = true;
+
+        loop {
+            if timer && let Err(e) = sleep_until(resolution.next(Utc::now())).await {
+                warn!("Failed to wait on the wall clock, the clock can be late after a suspend: {e}");
+                timer = false;
+            }
+            if !timer {
+                tokio::time::sleep(resolution.until_next(Utc::now())).await;
+            }
+
+            let mark = resolution.mark(&Local::now());
+            if mark == last {
+                continue;
+            }
+            last = mark;
+
+            if output.send(()).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// The current time in one of the zones, formatted
+#[derive(Debug, Default)]
+struct ZoneTime {
+    name: String,
+    text: String,
+    /// The time in the vertical format, there can be vertical and horizontal bars at the same time
+    vertical_text: String,
+    /// The UTC offset and the date, like "UTC-04:00 · Fri 16 Oct"
+    details: String,
+}
+
+impl ZoneTime {
+    fn new<T: TimeZone>(name: &str, time: DateTime<T>, format: &str, vertical_format: &str) -> Self
+    where
+        T::Offset: Display
+    {
+        Self {
+            name: name.to_string(),
+            text: time.format(format).to_string(),
+            vertical_text: time.format(vertical_format).to_string(),
+            details: time.format("UTC%:z · %a %d %b").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Clock {
+    format: String,
+    vertical_format: String,
+    width: u32,
+    zones: Vec<ClockZone>,
+    zones_on_hover: bool,
+    resolution: Resolution,
+
+    /// The local time first, then the time of every zone in the order of the config
+    times: Vec<ZoneTime>,
+    /// The index in `times` of the time shown
+    selected: usize,
+    hovered: bool,
+    scroll: Scroll,
+
+    calendar_paths: Vec<PathBuf>,
+    show_next_event: bool,
+    next_event_within: TimeDelta,
+    events: Vec<Event>,
+    /// The events starting soon, the window is longer than `next_event_within` so it lasts until the next reload
+    upcoming: Vec<Occurrence>,
+    /// Like "Standup in 12 min"
+    next_event: Option<String>,
+    /// The errors of the last load, so the same ones aren't reported on every reload
+    calendar_errors: Vec<String>,
+
+    calendar: calendar_window::State,
+}
+
+impl Clock {
+    fn tick(&mut self) {
+        let now = Utc::now();
+
+        let local = ZoneTime::new("Local", now.with_timezone(&Local), &self.format, &self.vertical_format);
+        let zones = self.zones.iter().map(|zone| {
+            let format = zone.format.as_deref().unwrap_or(&self.format);
+            ZoneTime::new(&zone.name, now.with_timezone(&zone.timezone), format, &self.vertical_format)
+        });
+
+        self.times = std::iter::once(local).chain(zones).collect();
+        self.next_event = self.find_next_event(now.with_timezone(&Local));
+    }
+
+    /// The first event, not lasting all day, that starts within `next_event_within`
+    fn find_next_event(&self, now: DateTime<Local>) -> Option<String> {
+        if !self.show_next_event {
+            return None;
+        }
+
+        let occurrence = self.upcoming
+            .iter()
+            .find(|occurrence| !occurrence.all_day && occurrence.start >= now && occurrence.start - now <= self.next_event_within)?;
+
+        // Rounded up, so it doesn't say "in 0 min" while the event hasn't started yet
+        let minutes = ((occurrence.start - now).num_seconds() + 59) / 60;
+        Some(format!("{} in {minutes} min", occurrence.summary))
+    }
+
+    fn load_events(&self) -> Task<crate::Message> {
+        if self.calendar_paths.is_empty() {
+            return Task::none();
+        }
+
+        let paths = self.calendar_paths.clone();
+        Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || calendar::load(&paths))
+                    .await
+                    .unwrap_or_default()
+            },
+            |(events, errors)| Message::EventsLoaded(events, errors).into()
+        )
+    }
+
+    fn events_loaded(&mut self, events: Vec<Event>, errors: Vec<CalendarError>) -> Task<crate::Message> {
+        debug!(events = events.len(), errors = errors.len(), "Calendars loaded");
+        self.events = events;
+
+        let now = Local::now();
+        self.upcoming = calendar::occurrences(&self.events, now, now + self.next_event_within + TimeDelta::from_std(CALENDAR_RELOAD).unwrap_or_default());
+        self.next_event = self.find_next_event(now);
+        self.calendar.refresh(&self.events);
+
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        if messages == self.calendar_errors {
+            return Task::none();
+        }
+        self.calendar_errors = messages;
+
+        for error in &errors {
+            warn!("{error}");
+        }
+
+        match errors.as_slice() {
+            [] => Task::none(),
+            [error] => Self::calendar_notification(error.to_string()),
+            [error, rest @ ..] => Self::calendar_notification(format!("{error} (and {} more calendar errors)", rest.len()))
+        }
+    }
+
+    fn calendar_notification(message: String) -> Task<crate::Message> {
+        Task::done(
+            crate::Message::NewNotif(
+                Notification::new_with_retry(
+                    notification::Level::Warning,
+                    message,
+                    Local::now(),
+                    Message::LoadEvents
+                )
+            )
+        )
+    }
+
+    /// The zones shown next to the selected one while the pointer is on the clock
+    fn others(&self) -> impl Iterator<Item = &ZoneTime> {
+        let shown = self.hovered && self.zones_on_hover;
+
+        self.times
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| shown && *i != self.selected)
+            .map(|(_, time)| time)
+    }
+
+    /// The time of a zone with its name, offset and date below it
+    fn zone_view<'a>(&self, time: &'a ZoneTime) -> Element<'a, crate::Message> {
+        column![
+            text(&time.text)
+                .size(22)
+                .line_height(LineHeight::Relative(1.0))
+                .style(text::primary),
+            text(format!("{} · {}", time.name, time.details))
+                .size(11)
+                .line_height(LineHeight::Relative(1.0))
+                .style(text::secondary)
+        ]
+        .align_x(Alignment::Center)
+        .into()
+    }
+
+    /// The vertical format stacks the parts of the time on separate lines, so the text is smaller and fills the bar's width
+    fn vertical_zone_view<'a>(&self, time: &'a ZoneTime, is_local: bool) -> Element<'a, crate::Message> {
+        let time_text = text(&time.vertical_text)
+            .size(24)
+            .center()
+            .width(Length::Fill)
+            .style(text::primary);
+
+        if is_local {
+            return time_text.into();
+        }
+
+        column![
+            time_text,
+            text(&time.name)
+                .size(12)
+                .center()
+                .width(Length::Fill)
+                .style(text::secondary)
+        ]
+        .into()
+    }
+
+    fn vertical_view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message> {
+        let selected = self.times.get(self.selected).map(|time| self.vertical_zone_view(time, self.selected == 0));
+        let others = self.others().map(|time| self.vertical_zone_view(time, false));
+
+        self.styled(
+            Column::with_children(selected.into_iter().chain(others))
+                .spacing(ctx.spacing)
+                .into(),
+            ctx
+        )
+        .padding(Padding::default().vertical(ctx.hpadding))
+        .width(Length::Fill)
+        .height(Length::Shrink)
+        .into()
+    }
+
+    fn styled<'a>(&self, content: Element<'a, crate::Message>, ctx: &'a Context) -> container::Container<'a, crate::Message> {
+        container(content)
+            .style(|theme: &Theme| {
+                let palette = theme.extended_palette();
+
+                container::Style::default()
+                    .background(palette.background.weak.color)
+                    .border(border::rounded(ctx.radius))
+            })
+    }
+}
+
+impl BarModule for Clock {
+    type Message = Message;
+
+    fn configure(&mut self, config: &Config) -> Task<crate::Message> {
+        self.format = config.clock.format.clone();
+        self.vertical_format = config.clock.vertical_format.clone();
+        self.width = config.clock.width;
+        self.zones = config.clock.zones.clone();
+        self.zones_on_hover = config.clock.zones_on_hover;
+
+        let zone_formats = self.zones.iter().filter_map(|zone| zone.format.as_deref());
+        self.resolution = Resolution::of([self.format.as_str(), self.vertical_format.as_str()].into_iter().chain(zone_formats));
+
+        // The selected zone could have been removed
+        if self.selected > self.zones.len() {
+            self.selected = 0;
+        }
+
+        self.show_next_event = config.calendar.next_event;
+        self.next_event_within = TimeDelta::minutes(config.calendar.next_event_within.into());
+        self.calendar.configure(!config.calendar.paths.is_empty());
+
+        self.tick();
+
+        // Reading the calendars again on every config change would report their errors again
+        if self.calendar_paths == config.calendar.paths {
+            return Task::none();
+        }
+
+        self.calendar_paths = config.calendar.paths.clone();
+        self.calendar_errors.clear();
+        if self.calendar_paths.is_empty() {
+            return Task::done(Message::EventsLoaded(Vec::new(), Vec::new()).into());
+        }
+        self.load_events()
+    }
+
+    fn update(&mut self, message: Message) -> Task<crate::Message> {
+        match message {
+            Message::Tick => self.tick(),
+            Message::Scrolled(delta) => {
+                let count = self.times.len().max(1) as i32;
+                // Scrolling down goes to the next zone
+                self.selected = (self.selected as i32 - self.scroll.steps(delta)).rem_euclid(count) as usize;
+            },
+            Message::Entered => self.hovered = true,
+            Message::Exited => self.hovered = false,
+
+            Message::LoadEvents => return self.load_events(),
+            Message::EventsLoaded(events, errors) => return self.events_loaded(events, errors),
+
+            Message::Calendar(message) => return self.calendar.update(message, &self.events),
+        }
+
+        Task::none()
+    }
+
+    fn view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message> {
+        let content = if ctx.is_vertical() {
+            self.vertical_view(ctx)
+        } else {
+            let selected: Option<Element<'a, crate::Message>> = self.times.get(self.selected).map(|time| {
+                if self.selected == 0 {
+                    text(&time.text)
+                        .size(36)
+                        .center()
+                        .width(self.width)
+                        .style(text::primary)
+                        .into()
+                } else {
+                    container(self.zone_view(time))
+                        .center_x(self.width)
+                        .into()
+                }
+            });
+            let others = self.others().map(|time| self.zone_view(time));
+            let next_event: Option<Element<'a, crate::Message>> = self.next_event.as_ref().map(|next_event| {
+                text(next_event)
+                    .size(14)
+                    .style(text::secondary)
+                    .into()
+            });
+
+            self.styled(
+                Row::with_children(selected.into_iter().chain(next_event).chain(others))
+                    .spacing(ctx.spacing * 2)
+                    .align_y(Alignment::Center)
+                    .into(),
+                ctx
+            )
+            .padding(Padding::default().horizontal(ctx.hpadding))
+            .width(Length::Shrink)
+            .height(Length::Fill)
+            .align_y(Alignment::Center)
+            .into()
+        };
+
+        let area = mouse_area(content).on_press(crate::Message::CalendarWindowToggle(None));
+
+        // Without zones, there is nothing to scroll through or show on hover
+        if self.zones.is_empty() {
+            return area.into();
+        }
+
+        area
+            .on_enter(Message::Entered.into())
+            .on_exit(Message::Exited.into())
+            .on_scroll(|delta| Message::Scrolled(delta).into())
+            .into()
+    }
+
+    fn subscription(&self) -> Subscription<crate::Message> {
+        let tick = Subscription::run_with(self.resolution, ticks).map(|_| Message::Tick.into());
+
+        if self.calendar_paths.is_empty() {
+            return tick;
+        }
+
+        Subscription::batch([
+            tick,
+            iced::time::every(CALENDAR_RELOAD).map(|_| Message::LoadEvents.into())
+        ])
+    }
+
+    fn popup_view<'a>(&'a self, ctx: &'a Context) -> Option<Element<'a, crate::Message>> {
+        Some(self.calendar.view(ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    fn utc(hour: u32, minute: u32, second: u32, millis: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 17, hour, minute, second).unwrap() + TimeDelta::milliseconds(millis.into())
+    }
+
+    #[test]
+    fn resolution_of_formats() {
+        assert_eq!(Resolution::of(["%H:%M", "%H\n%M"]), Resolution::Minute);
+        assert_eq!(Resolution::of(["%a %d %b %H:%M"]), Resolution::Minute);
+        assert_eq!(Resolution::of(["%H:%M", "%H:%M:%S"]), Resolution::Second);
+        // %T and %r expand to a time with seconds
+        assert_eq!(Resolution::of(["%T"]), Resolution::Second);
+        assert_eq!(Resolution::of(["%r"]), Resolution::Second);
+        assert_eq!(Resolution::of(["%H:%M:%S%.3f"]), Resolution::Second);
+    }
+
+    #[test]
+    fn next_boundary() {
+        assert_eq!(Resolution::Minute.until_next(utc(9, 41, 0, 0)), Duration::from_millis(60_005));
+        assert_eq!(Resolution::Minute.until_next(utc(9, 41, 59, 990)), Duration::from_millis(15));
+        assert_eq!(Resolution::Second.until_next(utc(9, 41, 12, 250)), Duration::from_millis(755));
+    }
+
+    #[test]
+    fn next_change_on_the_wall_clock() {
+        assert_eq!(Resolution::Minute.next(utc(9, 41, 0, 0)), utc(9, 42, 0, 5));
+        assert_eq!(Resolution::Second.next(utc(9, 41, 12, 250)), utc(9, 41, 13, 5));
+    }
+
+    #[tokio::test]
+    async fn clock_resumes_late() {
+        // Resumed after the time it was sleeping until, the timer fires right away
+        let woke = tokio::time::timeout(Duration::from_secs(1), sleep_until(Utc::now() - TimeDelta::minutes(30))).await;
+        assert!(matches!(woke, Ok(Ok(()))), "{woke:?}");
+
+        let ticked = Resolution::Minute.mark(&utc(9, 41, 0, 5));
+        assert_eq!(Resolution::Minute.mark(&utc(9, 41, 59, 0)), ticked);
+        // Woken up after the suspend, past the minute it was sleeping until
+        assert_ne!(Resolution::Minute.mark(&utc(10, 17, 30, 0)), ticked);
+    }
+
+    #[test]
+    fn time_zone_change_ticks() {
+        let time = utc(9, 41, 0, 5);
+        let moved = time.with_timezone(&FixedOffset::east_opt(2 * 3600).unwrap());
+
+        assert_ne!(Resolution::Minute.mark(&moved), Resolution::Minute.mark(&time));
+    }
+}
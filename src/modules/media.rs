@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use iced::{Alignment, Element, Length, Padding, Subscription, Task, Theme, border, widget::{Space, container, text}};
+use mpris_client_async::{Mpris, Player};
+use tracing::{debug, error, info};
+
+use super::{BarModule, Context};
+use crate::media_utils::{self, MprisEvent};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    PlayerEvent(MprisEvent),
+
+    /// This runs when a new mpris object has been returned, and replaces the one in the state
+    NewPlayers(Result<Vec<Arc<Player>>, zbus::Error>),
+    TrackedPlayer(Option<Arc<Player>>),
+}
+
+#[derive(Default)]
+pub struct Media {
+    // An mpris instance is made in subscription, and every time a player_change event occurs it sends an arc copy.
+    mpris: Option<Arc<Mpris<'static>>>,
+
+    tracked_player: Option<Arc<Player>>,
+    players: Vec<Arc<Player>>,
+}
+
+impl BarModule for Media {
+    type Message = Message;
+
+    fn update(&mut self, message: Message) -> Task<crate::Message> {
+        match message {
+            Message::PlayerEvent(event) => {
+                match event {
+                    MprisEvent::StreamEnded => {
+                        self.mpris = None;
+                        self.tracked_player = None;
+
+                        info!("MPRIS stream ended");
+
+                        Task::none()
+                    },
+                    MprisEvent::NewInstance(instance) => {
+                        info!("New MPRIS instance");
+
+                        self.mpris = Some(instance.clone());
+                        Task::perform(async move {instance.get_players().await}, |players| Message::NewPlayers(players).into())
+                    },
+                    MprisEvent::Event(event) => {
+                        debug!(?event, "New MPRIS event");
+
+                        use mpris_client_async::PlayerEvent::*;
+                        match event {
+                            Connected(player) => self.players.push(player),
+                            Disconnected(player) => {
+                                // If the player was the tracked, clear it
+                                if let Some(tracked) = self.tracked_player.as_ref() && tracked.dbus_name() == player.dbus_name() {
+                                    self.tracked_player = None;
+                                }
+
+                                self.players.retain(|other| player.dbus_name() != other.dbus_name());
+                            }
+                        };
+
+                        let cloned = self.players.iter().map(|player| player.clone()).collect();
+                        Task::perform(media_utils::get_tracked_player(cloned), |player| Message::TrackedPlayer(player).into())
+                    }
+                }
+            },
+
+            Message::NewPlayers(maybe_players) => {
+                self.tracked_player = None;
+                match maybe_players {
+                    Ok(players) => self.players = players,
+                    Err(e) => error!("get_players on mpris object returned with error: {e}")
+                }
+
+                let cloned: Vec<Arc<Player>> = self.players.iter().map(|player| player.clone()).collect();
+                Task::perform(media_utils::get_tracked_player(cloned), |player| Message::TrackedPlayer(player).into())
+            },
+            Message::TrackedPlayer(player) => {
+                if let Some(player) = player.as_ref() {
+                    debug!(player = %player.dbus_name(), "Tracking player");
+                }
+                self.tracked_player = player;
+
+                Task::none()
+            }
+        }
+    }
+
+    /// The currently playing player, or nothing if there is none
+    fn view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message> {
+        let Some(player) = &self.tracked_player else {
+            return Space::new().into();
+        };
+
+        // org.mpris.MediaPlayer2.spotify -> spotify
+        let name = player.dbus_name().to_string();
+        let name = name.trim_start_matches("org.mpris.MediaPlayer2.").to_string();
+
+        // On vertical bars the name is wrapped inside the bar's width
+        let (size, padding, width, height) = if ctx.is_vertical() {
+            (14, Padding::default().vertical(ctx.hpadding), Length::Fill, Length::Shrink)
+        } else {
+            (24, Padding::default().horizontal(ctx.hpadding), Length::Shrink, Length::Fill)
+        };
+
+        container(
+            text(name)
+                .size(size)
+                .center()
+                .style(text::primary)
+        )
+        .padding(padding)
+        .width(width)
+        .height(height)
+        .align_x(Alignment::Center)
+        .align_y(Alignment::Center)
+        .style(|theme: &Theme| {
+            let palette = theme.extended_palette();
+
+            container::Style::default()
+                .background(palette.background.weak.color)
+                .border(border::rounded(ctx.radius))
+        })
+        .into()
+    }
+
+    fn subscription(&self) -> Subscription<crate::Message> {
+        Subscription::run(media_utils::mpris_subscription)
+            .map(Message::PlayerEvent)
+            .map(crate::Message::from)
+    }
+}
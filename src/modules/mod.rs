@@ -0,0 +1,196 @@
+//! The widgets of the bar. Every module is a self contained [BarModule] in its own file,
+//! and is registered once in the `modules!` invocation at the bottom of this file
+
+use std::fmt::Debug;
+
+use iced::{Element, Subscription, Task, Theme, mouse::ScrollDelta, widget::{Space, container}};
+
+use crate::{appearance::ColorScheme, config::{Config, Position}};
+
+pub mod clock;
+pub mod weather;
+pub mod media;
+pub mod timer;
+pub mod notifications;
+
+/// Appearance settings shared by every module
+#[derive(Debug, Clone)]
+pub struct Context {
+    pub theme: Theme,
+    pub radius: i32,
+    pub spacing: u32,
+    pub hpadding: u32,
+    /// Modules render a compact version of themselves on vertical bars
+    pub position: Position,
+}
+
+impl Context {
+    /// The context with the theme of the desktop's color scheme
+    pub fn new(config: &Config, scheme: ColorScheme) -> Self {
+        Self {
+            theme: config.current_theme(scheme).clone(),
+            ..Self::from(config)
+        }
+    }
+
+    /// The context of one of the bars, which can be on another edge than the main one
+    pub fn for_bar(config: &Config, scheme: ColorScheme, bar: &str) -> Self {
+        Self {
+            position: config.bars.get(bar).map(|instance| instance.bar.position).unwrap_or(config.bar.position),
+            ..Self::new(config, scheme)
+        }
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.position.is_vertical()
+    }
+}
+
+impl From<&Config> for Context {
+    fn from(config: &Config) -> Self {
+        Self {
+            theme: config.theme.clone(),
+            radius: config.radius.into(),
+            spacing: config.spacing,
+            hpadding: config.hpadding,
+            position: config.bar.position
+        }
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::from(&Config::default())
+    }
+}
+
+/// A widget of the bar.
+/// Its messages are wrapped into [crate::Message] by the registry, so `Self::Message::Variant.into()` can be used to produce a [crate::Message]
+pub trait BarModule: Default {
+    type Message: Debug + Clone + Send + 'static;
+
+    /// Called on startup and every time the config is reloaded
+    fn configure(&mut self, _config: &Config) -> Task<crate::Message> {
+        Task::none()
+    }
+
+    fn update(&mut self, message: Self::Message) -> Task<crate::Message>;
+
+    /// The widget shown in the bar
+    fn view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message>;
+
+    fn subscription(&self) -> Subscription<crate::Message> {
+        Subscription::none()
+    }
+
+    /// The content of the module's popup window, if it has one
+    fn popup_view<'a>(&'a self, _ctx: &'a Context) -> Option<Element<'a, crate::Message>> {
+        None
+    }
+}
+
+/// Turns scroll events into steps, one per notch of a mouse wheel, or one per [Scroll::PIXELS] scrolled on a touchpad
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Scroll {
+    /// The pixels scrolled on a touchpad since the last step
+    pixels: f32,
+}
+
+impl Scroll {
+    pub const PIXELS: f32 = 40.0;
+
+    /// The steps scrolled, positive when scrolling up
+    pub fn steps(&mut self, delta: ScrollDelta) -> i32 {
+        match delta {
+            // signum is 1 for 0, horizontal scrolling has no y
+            ScrollDelta::Lines { y, .. } if y == 0.0 => 0,
+            ScrollDelta::Lines { y, .. } => y.signum() as i32,
+            ScrollDelta::Pixels { y, .. } => {
+                self.pixels += y;
+                let steps = (self.pixels / Self::PIXELS).trunc();
+                self.pixels -= steps * Self::PIXELS;
+                steps as i32
+            }
+        }
+    }
+}
+
+/// A thin line between modules, across the direction of the bar
+fn separator<'a>(ctx: &Context) -> Element<'a, crate::Message> {
+    let (width, height) = if ctx.is_vertical() { (30, 2) } else { (2, 30) };
+
+    container(Space::new())
+        .width(width)
+        .height(height)
+        .style(|theme: &Theme| {
+            let palette = theme.extended_palette();
+
+            container::Style::default()
+                .background(palette.background.weakest.color)
+        })
+        .into()
+}
+
+/// Generates the [Module] names used in the config layout, the [Message] wrapper and the [Modules] collection.
+/// `field: Variant => Type`, where Variant lowercased is the name of the module in the config
+macro_rules! modules {
+    ($($field:ident: $variant:ident => $module:ty),* $(,)?) => {
+        /// A module that can be placed in the layout
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        pub enum Module {
+            $($variant,)*
+            /// A thin line between modules
+            Separator,
+        }
+
+        #[derive(Debug, Clone)]
+        pub enum Message {
+            $($variant(<$module as BarModule>::Message),)*
+        }
+
+        $(
+            impl From<<$module as BarModule>::Message> for crate::Message {
+                fn from(message: <$module as BarModule>::Message) -> Self {
+                    crate::Message::Module(Message::$variant(message))
+                }
+            }
+        )*
+
+        #[derive(Default)]
+        pub struct Modules {
+            $(pub $field: $module,)*
+        }
+
+        impl Modules {
+            pub fn configure(&mut self, config: &Config) -> Task<crate::Message> {
+                Task::batch([$(self.$field.configure(config)),*])
+            }
+
+            pub fn update(&mut self, message: Message) -> Task<crate::Message> {
+                match message {
+                    $(Message::$variant(message) => self.$field.update(message),)*
+                }
+            }
+
+            pub fn view<'a>(&'a self, module: Module, ctx: &'a Context) -> Element<'a, crate::Message> {
+                match module {
+                    $(Module::$variant => self.$field.view(ctx),)*
+                    Module::Separator => separator(ctx),
+                }
+            }
+
+            pub fn subscription(&self) -> Subscription<crate::Message> {
+                Subscription::batch([$(self.$field.subscription()),*])
+            }
+        }
+    };
+}
+
+modules! {
+    clock: Clock => clock::Clock,
+    weather: Weather => weather::Weather,
+    media: Media => media::Media,
+    timer: Timer => timer::Timer,
+    notifications: Notifications => notifications::Notifications,
+}
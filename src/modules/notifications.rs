@@ -0,0 +1,872 @@
+//! The notifications of the bar, an indicator with the number of unread ones that opens a popup listing them.
+//! New notifications are shown as toasts in the corner of the screen.
+//! With the notification server enabled, the notifications of desktop apps are listed and shown too.
+//! Do not disturb hides the toasts, by hand, on a schedule or while a media player is fullscreen
+
+use std::{collections::{HashMap, VecDeque}, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use chrono::{DateTime, Local, NaiveTime};
+use iced::{Alignment, Element, Length, Padding, Subscription, Task, Theme, border, widget::{Column, Row, container, mouse_area, text}};
+use tracing::{debug, warn};
+
+use super::{BarModule, Context};
+use crate::{
+    config::{Config, DndConfig},
+    media_utils,
+    notification::{Level, Notification, Origin},
+    notification_history,
+    notification_server::{self, CloseReason, DesktopNotification, Expiry, Handle, Urgency},
+    windows::{notification_window, toast_window}
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// The popup was opened, so every notification is read
+    Read,
+    Dismiss(u64),
+    /// Runs the retry of a notification and dismisses it, a new one comes if it fails again
+    Retry(u64),
+    ClearAll,
+
+    /// An event of the notification server
+    Server(notification_server::Event),
+    /// Closes the toasts that expired
+    ExpireToasts,
+    /// Closes a toast, the notification stays in the list
+    CloseToast(u64),
+    /// Invokes an action of an app's notification by its key, and closes the toast
+    Action(u64, String),
+    /// The pointer entered a toast, its expiry is paused
+    ToastEntered(u64),
+    ToastLeft(u64),
+
+    ToggleDnd,
+    SetDnd(bool),
+    /// Turns do not disturb on or off when a schedule starts or ends
+    CheckSchedules,
+    /// Whether a media player is fullscreen
+    Fullscreen(bool),
+
+    /// Drops the old notifications from the history
+    PruneHistory,
+}
+
+/// A notification of the list
+#[derive(Debug)]
+pub struct Entry {
+    /// Unique for the lifetime of the bar, indices change when notifications are dismissed
+    pub id: u64,
+    pub notification: Notification,
+    pub read: bool,
+    /// The times the same notification came
+    pub count: u32,
+    /// When it last came, the time of the notification is when it first came
+    pub last_seen: DateTime<Local>,
+}
+
+/// When a toast expires
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Countdown {
+    /// Stays until it's closed
+    Sticky,
+    Running(Instant),
+    /// Hovered, with the time that was left
+    Paused(Duration),
+}
+
+impl Countdown {
+    fn new(timeout: Option<Duration>) -> Self {
+        match timeout {
+            Some(timeout) => Self::Running(Instant::now() + timeout),
+            None => Self::Sticky
+        }
+    }
+
+    fn pause(self) -> Self {
+        match self {
+            Self::Running(expires) => Self::Paused(expires.saturating_duration_since(Instant::now())),
+            countdown => countdown
+        }
+    }
+
+    fn resume(self) -> Self {
+        match self {
+            Self::Paused(left) => Self::Running(Instant::now() + left),
+            countdown => countdown
+        }
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        matches!(self, Self::Running(expires) if *expires <= now)
+    }
+}
+
+/// A notification shown in the corner of the screen
+#[derive(Debug)]
+struct Toast {
+    /// The id of the entry
+    id: u64,
+    countdown: Countdown,
+}
+
+/// How long the toast of a notification of the bar is shown, errors stay until they're dismissed
+fn level_timeout(level: Level) -> Option<Duration> {
+    match level {
+        Level::Notice => Some(Duration::from_secs(5)),
+        Level::Warning => Some(Duration::from_secs(10)),
+        Level::Error => None,
+    }
+}
+
+/// Limits the toasts of every source to a number per minute
+#[derive(Debug, Default)]
+struct RateLimit {
+    per_minute: usize,
+    /// When the toasts of the last minute were shown, by source, the oldest first
+    shown: HashMap<String, VecDeque<Instant>>,
+}
+
+impl RateLimit {
+    /// Counts a toast of the source, false if it already showed too many in the last minute
+    fn allow(&mut self, source: &str, now: Instant) -> bool {
+        let shown = self.shown.entry(source.to_string()).or_default();
+        while shown.front().is_some_and(|time| now.duration_since(*time) >= Duration::from_secs(60)) {
+            shown.pop_front();
+        }
+
+        if shown.len() >= self.per_minute {
+            return false;
+        }
+        shown.push_back(now);
+        true
+    }
+}
+
+/// Whether do not disturb is on, and why
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DndMode {
+    Off,
+    /// Turned on by hand
+    Manual,
+    Scheduled,
+    Fullscreen,
+}
+
+impl DndMode {
+    /// Shown on the bar while it's on
+    fn label(&self) -> Option<&'static str> {
+        match self {
+            DndMode::Off => None,
+            DndMode::Manual => Some("DND"),
+            DndMode::Scheduled => Some("DND ☾"),
+            DndMode::Fullscreen => Some("DND ▶"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Dnd {
+    config: DndConfig,
+    /// Turned on or off by hand, until a schedule starts or ends or a player goes fullscreen or leaves it
+    manual: Option<bool>,
+    scheduled: bool,
+    fullscreen: bool,
+}
+
+impl Dnd {
+    fn mode(&self) -> DndMode {
+        match self.manual {
+            Some(true) => DndMode::Manual,
+            Some(false) => DndMode::Off,
+            None if self.fullscreen => DndMode::Fullscreen,
+            None if self.scheduled => DndMode::Scheduled,
+            None => DndMode::Off
+        }
+    }
+
+    fn toggle(&mut self) {
+        self.manual = Some(self.mode() == DndMode::Off);
+    }
+
+    fn check_schedules(&mut self, now: NaiveTime) {
+        let scheduled = self.config.schedules.iter().any(|schedule| schedule.contains(now));
+        if scheduled != self.scheduled {
+            self.scheduled = scheduled;
+            self.manual = None;
+        }
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        if fullscreen != self.fullscreen {
+            self.fullscreen = fullscreen;
+            self.manual = None;
+        }
+    }
+
+    /// Whether the toast of a notification is shown
+    fn allows(&self, notification: &Notification) -> bool {
+        if self.mode() == DndMode::Off {
+            return true;
+        }
+
+        match (&notification.origin, notification.level) {
+            (Origin::App { .. }, Level::Error) => self.config.allow_critical,
+            (Origin::Bar, Level::Error) => self.config.allow_errors,
+            _ => false
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Notifications {
+    entries: Vec<Entry>,
+    next_id: u64,
+    /// The oldest first
+    toasts: Vec<Toast>,
+
+    server: bool,
+    /// How long the notifications of apps are shown, if they don't ask for another timeout
+    timeout: Duration,
+    max_toasts: usize,
+    max_entries: usize,
+    rate_limit: RateLimit,
+    history_days: u32,
+    /// Where notifications are saved, None if the history is turned off
+    history: Option<PathBuf>,
+    /// The history is only loaded on startup, not on config reloads
+    loaded: bool,
+    /// Set once the server runs
+    handle: Option<Handle>,
+    dnd: Dnd,
+}
+
+impl Notifications {
+    /// Lists a notification, or counts it again if the same one is listed.
+    /// The id of its entry, and whether it's new
+    fn list(&mut self, mut notification: Notification, read: bool) -> (u64, bool) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.notification.same_as(&notification)) {
+            entry.count += 1;
+            entry.last_seen = notification.time;
+            entry.read &= read;
+
+            // The newest one is kept, for its retry and the id of its app
+            notification.time = entry.notification.time;
+            entry.notification = notification;
+            return (entry.id, false);
+        }
+
+        let id = self.next_id;
+        self.entries.push(Entry { id, last_seen: notification.time, notification, read, count: 1 });
+        self.next_id += 1;
+        self.cap();
+
+        (id, true)
+    }
+
+    /// Drops the notifications past the limit, the oldest read ones without a toast first
+    fn cap(&mut self) {
+        while self.entries.len() > self.max_entries {
+            let toasted = |entry: &Entry| self.toasts.iter().any(|toast| toast.id == entry.id);
+            let oldest = |read_only: bool| self.entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| (entry.read || !read_only) && !toasted(entry))
+                .min_by_key(|(_, entry)| entry.last_seen)
+                .map(|(index, _)| index);
+
+            let index = oldest(true).or_else(|| oldest(false)).unwrap_or(0);
+            let entry = self.entries.remove(index);
+            self.toasts.retain(|toast| toast.id != entry.id);
+        }
+    }
+
+    /// Lists a new notification. Every one is saved to the history, only the list collapses the same ones
+    fn push(&mut self, notification: Notification) -> u64 {
+        if let Some(path) = &self.history {
+            notification_history::append(path, &notification);
+        }
+
+        self.list(notification, false).0
+    }
+
+    /// Lists the notifications of the previous runs, they were seen already
+    fn load_history(&mut self, path: &Path) {
+        for record in notification_history::load(path, self.history_days) {
+            self.list(record.into(), true);
+        }
+    }
+
+    /// Whether a notification gets a toast, with do not disturb and the rate limit of its source
+    fn toast_allowed(&mut self, notification: &Notification) -> bool {
+        self.dnd.allows(notification) && self.rate_limit.allow(notification.source(), Instant::now())
+    }
+
+    /// Lists a notification of the bar, and shows its toast unless do not disturb is on
+    pub fn notify_bar(&mut self, notification: Notification) {
+        let countdown = Countdown::new(level_timeout(notification.level));
+        let shown = self.toast_allowed(&notification);
+
+        let id = self.push(notification);
+        if shown {
+            self.show_toast(id, countdown);
+        }
+    }
+
+    fn unread(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|entry| !entry.read)
+    }
+
+    /// The most severe level of the unread notifications
+    fn severity(&self) -> Option<Level> {
+        self.unread().map(|entry| entry.notification.level).max()
+    }
+
+    fn entry(&self, id: u64) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// The id an app knows a notification by
+    fn app_id(&self, id: u64) -> Option<u32> {
+        match self.entry(id)?.notification.origin {
+            Origin::App { id, .. } => Some(id),
+            Origin::Bar => None
+        }
+    }
+
+    fn dismiss(&mut self, id: u64) -> (Option<Entry>, Task<crate::Message>) {
+        let task = self.close_toast(id, CloseReason::Dismissed);
+        let entry = self.entries.iter().position(|entry| entry.id == id).map(|index| self.entries.remove(index));
+        (entry, task)
+    }
+
+    /// Removes the toast of a notification, and tells the app it was closed
+    fn close_toast(&mut self, id: u64, reason: CloseReason) -> Task<crate::Message> {
+        let Some(index) = self.toasts.iter().position(|toast| toast.id == id) else {
+            return Task::none();
+        };
+        self.toasts.remove(index);
+
+        match self.app_id(id) {
+            Some(app_id) => self.closed(app_id, reason),
+            None => Task::none()
+        }
+    }
+
+    /// Tells the app one of its notifications was closed
+    fn closed(&self, app_id: u32, reason: CloseReason) -> Task<crate::Message> {
+        match self.handle.clone() {
+            Some(handle) => Task::perform(handle.closed(app_id, reason), |_| crate::Message::Nothing),
+            None => Task::none()
+        }
+    }
+
+    /// Shows a toast, or restarts it if it's already shown
+    fn show_toast(&mut self, id: u64, countdown: Countdown) {
+        // The entry could have been dropped by the limit of the list already
+        if self.entry(id).is_none() {
+            return;
+        }
+
+        self.toasts.retain(|toast| toast.id != id);
+        self.toasts.push(Toast { id, countdown });
+    }
+
+    fn toast_mut(&mut self, id: u64) -> Option<&mut Toast> {
+        self.toasts.iter_mut().find(|toast| toast.id == id)
+    }
+
+    fn notify(&mut self, notification: DesktopNotification) -> Task<crate::Message> {
+        debug!(?notification, "Desktop notification");
+
+        // Critical notifications stay until they're closed, like in other servers
+        let countdown = Countdown::new(match (notification.urgency, notification.expiry) {
+            (Urgency::Critical, _) | (_, Expiry::Never) => None,
+            (_, Expiry::Default) => Some(self.timeout),
+            (_, Expiry::Milliseconds(milliseconds)) => Some(Duration::from_millis(milliseconds.into())),
+        });
+
+        let level = match notification.urgency {
+            Urgency::Critical => Level::Error,
+            Urgency::Low | Urgency::Normal => Level::Notice,
+        };
+        let app_id = notification.id;
+        let new = Notification::new(level, notification.summary, Local::now())
+            .with_origin(Origin::App {
+                name: notification.app_name,
+                id: app_id,
+                body: notification.body,
+                actions: notification.actions
+            });
+        let shown = self.toast_allowed(&new);
+
+        // A replacing notification updates the one it replaces in place, only the first one is saved to the history
+        let replaced = self.entries
+            .iter_mut()
+            .find(|entry| notification.replaces && matches!(entry.notification.origin, Origin::App { id, .. } if id == app_id));
+
+        let id = match replaced {
+            Some(entry) => {
+                entry.notification = new;
+                entry.read = false;
+                entry.id
+            },
+            None => self.push(new)
+        };
+
+        if shown {
+            self.show_toast(id, countdown);
+            Task::none()
+        } else {
+            // Without a toast the notification is closed right away, the app still gets the signal
+            self.toasts.retain(|toast| toast.id != id);
+            self.closed(app_id, CloseReason::Expired)
+        }
+    }
+
+    fn expire_toasts(&mut self) -> Task<crate::Message> {
+        let now = Instant::now();
+        let expired: Vec<u64> = self.toasts
+            .iter()
+            .filter(|toast| toast.countdown.expired(now))
+            .map(|toast| toast.id)
+            .collect();
+
+        Task::batch(expired.into_iter().map(|id| self.close_toast(id, CloseReason::Expired)).collect::<Vec<_>>())
+    }
+
+    /// The number of toasts shown, the surface of the toasts is sized to fit them
+    pub fn toasts_shown(&self) -> usize {
+        self.toasts.len().min(self.max_toasts)
+    }
+
+    /// The newest toasts, the newest on the edge of the screen
+    pub fn toasts_view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message> {
+        let toasts = self.toasts
+            .iter()
+            .rev()
+            .take(self.max_toasts)
+            .filter_map(|toast| self.entry(toast.id));
+
+        toast_window::view(toasts, ctx)
+    }
+}
+
+impl BarModule for Notifications {
+    type Message = Message;
+
+    fn configure(&mut self, config: &Config) -> Task<crate::Message> {
+        self.server = config.notifications.server;
+        self.timeout = Duration::from_secs(config.notifications.timeout.into());
+        // The config rejects 0, the list always keeps the newest notification
+        self.max_toasts = config.notifications.max_toasts.max(1) as usize;
+        self.max_entries = config.notifications.max_entries.max(1) as usize;
+        self.rate_limit.per_minute = config.notifications.toasts_per_minute.max(1) as usize;
+        self.history_days = config.notifications.history_days;
+        self.history = (self.history_days > 0).then(notification_history::path);
+
+        if !self.loaded && let Some(path) = self.history.clone() {
+            self.loaded = true;
+            self.load_history(&path);
+        }
+        self.cap();
+
+        self.dnd.config = config.notifications.dnd.clone();
+        self.dnd.check_schedules(Local::now().time());
+        if !self.dnd.config.fullscreen {
+            self.dnd.set_fullscreen(false);
+        }
+
+        // The server stops with its subscription
+        if !self.server {
+            self.handle = None;
+        }
+
+        Task::none()
+    }
+
+    fn update(&mut self, message: Message) -> Task<crate::Message> {
+        match message {
+            Message::Read => self.entries.iter_mut().for_each(|entry| entry.read = true),
+            Message::Dismiss(id) => return self.dismiss(id).1,
+            Message::Retry(id) => {
+                let (Some(entry), task) = self.dismiss(id) else {
+                    return Task::none();
+                };
+
+                let retry = entry.notification.retry().unwrap_or_else(|e| {
+                    warn!("{e}");
+                    Task::none()
+                });
+                return Task::batch([task, retry]);
+            },
+            Message::ClearAll => {
+                let ids: Vec<u64> = self.toasts.iter().map(|toast| toast.id).collect();
+                let tasks: Vec<_> = ids.into_iter().map(|id| self.close_toast(id, CloseReason::Dismissed)).collect();
+                self.entries.clear();
+                return Task::batch(tasks);
+            },
+
+            Message::Server(event) => match event {
+                notification_server::Event::Started(handle) => self.handle = Some(handle),
+                notification_server::Event::Notify(notification) => return self.notify(notification),
+                // The server already told the app, only the toast is removed
+                notification_server::Event::Close(app_id) => {
+                    let entry = self.entries.iter().find(|entry| matches!(entry.notification.origin, Origin::App { id, .. } if id == app_id));
+                    if let Some(id) = entry.map(|entry| entry.id) {
+                        self.toasts.retain(|toast| toast.id != id);
+                    }
+                }
+            },
+            Message::ExpireToasts => return self.expire_toasts(),
+            Message::CloseToast(id) => return self.close_toast(id, CloseReason::Dismissed),
+            Message::Action(id, key) => {
+                let (Some(app_id), Some(handle)) = (self.app_id(id), self.handle.clone()) else {
+                    return Task::none();
+                };
+
+                return Task::batch([
+                    Task::perform(handle.action_invoked(app_id, key), |_| crate::Message::Nothing),
+                    self.close_toast(id, CloseReason::Dismissed)
+                ]);
+            },
+            Message::ToastEntered(id) => if let Some(toast) = self.toast_mut(id) {
+                toast.countdown = toast.countdown.pause();
+            },
+            Message::ToastLeft(id) => if let Some(toast) = self.toast_mut(id) {
+                toast.countdown = toast.countdown.resume();
+            },
+
+            Message::ToggleDnd => self.dnd.toggle(),
+            Message::SetDnd(on) => self.dnd.manual = Some(on),
+            Message::CheckSchedules => self.dnd.check_schedules(Local::now().time()),
+            Message::Fullscreen(fullscreen) => {
+                debug!(fullscreen, "Fullscreen players changed");
+                self.dnd.set_fullscreen(fullscreen);
+            },
+
+            Message::PruneHistory => if let Some(path) = self.history.clone() {
+                let days = self.history_days;
+                return Task::perform(
+                    tokio::task::spawn_blocking(move || notification_history::prune(&path, days)),
+                    |_| crate::Message::Nothing
+                );
+            },
+        }
+
+        Task::none()
+    }
+
+    /// The number of unread notifications, on the color of the most severe one, and the mode of do not disturb
+    fn view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message> {
+        let unread = self.unread().count();
+        let severity = self.severity();
+
+        let text_color = move |theme: &Theme| {
+            let palette = theme.extended_palette();
+            text::Style {
+                color: Some(match severity {
+                    Some(level) => notification_window::level_pair(theme, level).text,
+                    None => palette.background.strong.color
+                })
+            }
+        };
+
+        let dnd: Option<Element<'a, crate::Message>> = self.dnd.mode().label().map(|label| text(label).size(14).center().style(text_color).into());
+        let count: Element<'a, crate::Message> = text(unread.to_string()).size(20).center().style(text_color).into();
+        let children = dnd.into_iter().chain([count]);
+
+        // Stacked on vertical bars, to fit in their width
+        let content: Element<'a, crate::Message> = if ctx.is_vertical() {
+            Column::with_children(children).spacing(2).align_x(Alignment::Center).into()
+        } else {
+            Row::with_children(children).spacing(6).align_y(Alignment::Center).into()
+        };
+
+        let (padding, width, height) = if ctx.is_vertical() {
+            (Padding::default().vertical(ctx.hpadding), Length::Fill, Length::Shrink)
+        } else {
+            (Padding::default().horizontal(ctx.hpadding * 2), Length::Shrink, Length::Fill)
+        };
+
+        let content = container(content)
+        .padding(padding)
+        .width(width)
+        .height(height)
+        .align_x(Alignment::Center)
+        .align_y(Alignment::Center)
+        .style(move |theme: &Theme| {
+            let background = match severity {
+                Some(level) => notification_window::level_pair(theme, level).color,
+                None => theme.extended_palette().background.weak.color
+            };
+
+            container::Style::default()
+                .background(background)
+                .border(border::rounded(ctx.radius))
+        });
+
+        mouse_area(content)
+            .on_press(crate::Message::NotificationWindowToggle(None))
+            .on_right_press(Message::ToggleDnd.into())
+            .into()
+    }
+
+    fn subscription(&self) -> Subscription<crate::Message> {
+        let server = if self.server {
+            Subscription::run(notification_server::serve).map(|event| Message::Server(event).into())
+        } else {
+            Subscription::none()
+        };
+
+        // Only checked while a toast can expire
+        let expiry = if self.toasts.iter().any(|toast| matches!(toast.countdown, Countdown::Running(_))) {
+            iced::time::every(Duration::from_millis(250)).map(|_| Message::ExpireToasts.into())
+        } else {
+            Subscription::none()
+        };
+
+        // The schedules are checked often enough to start and end within a minute
+        let schedules = if self.dnd.config.schedules.is_empty() {
+            Subscription::none()
+        } else {
+            iced::time::every(Duration::from_secs(30)).map(|_| Message::CheckSchedules.into())
+        };
+
+        let fullscreen = if self.dnd.config.fullscreen {
+            Subscription::run(media_utils::fullscreen_subscription).map(|fullscreen| Message::Fullscreen(fullscreen).into())
+        } else {
+            Subscription::none()
+        };
+
+        // The history is pruned on startup too, this is for bars running for days
+        let prune = if self.history.is_some() {
+            iced::time::every(Duration::from_secs(24 * 60 * 60)).map(|_| Message::PruneHistory.into())
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([server, expiry, schedules, fullscreen, prune])
+    }
+
+    fn popup_view<'a>(&'a self, ctx: &'a Context) -> Option<Element<'a, crate::Message>> {
+        Some(notification_window::view(&self.entries, self.dnd.mode(), ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// With the default config, without the history
+    fn configured() -> Notifications {
+        let mut config = Config::default();
+        config.notifications.history_days = 0;
+
+        let mut notifications = Notifications::default();
+        let _ = notifications.configure(&config);
+        notifications
+    }
+
+    fn notifications(levels: &[Level]) -> Notifications {
+        let mut notifications = configured();
+        for level in levels {
+            notifications.push(Notification::new(*level, "test", Local::now()));
+        }
+        notifications
+    }
+
+    fn desktop(id: u32, replaces: bool, summary: &str) -> DesktopNotification {
+        DesktopNotification {
+            id,
+            replaces,
+            app_name: String::from("notify-send"),
+            summary: String::from(summary),
+            body: String::new(),
+            actions: Vec::new(),
+            urgency: Urgency::Normal,
+            expiry: Expiry::Default,
+        }
+    }
+
+    #[test]
+    fn severity_of_unread() {
+        let mut notifications = notifications(&[Level::Notice, Level::Error, Level::Warning]);
+        assert_eq!(notifications.severity(), Some(Level::Error));
+
+        notifications.entries[1].read = true;
+        assert_eq!(notifications.severity(), Some(Level::Warning));
+
+        let _ = notifications.update(Message::Read);
+        assert_eq!(notifications.severity(), None);
+    }
+
+    #[test]
+    fn dismiss_by_id() {
+        let mut notifications = notifications(&[Level::Notice, Level::Warning, Level::Error]);
+
+        let _ = notifications.update(Message::Dismiss(1));
+        let _ = notifications.update(Message::Dismiss(1));
+        let ids: Vec<u64> = notifications.entries.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![0, 2]);
+
+        // Ids aren't reused after clearing
+        let _ = notifications.update(Message::ClearAll);
+        notifications.push(Notification::new(Level::Notice, "test", Local::now()));
+        assert_eq!(notifications.entries[0].id, 3);
+    }
+
+    #[test]
+    fn replacing_notification_updates_in_place() {
+        let mut notifications = configured();
+
+        let _ = notifications.notify(desktop(7, false, "Downloading 10%"));
+        let _ = notifications.notify(desktop(8, false, "Other"));
+        let _ = notifications.notify(desktop(7, true, "Downloading 90%"));
+
+        assert_eq!(notifications.entries.len(), 2);
+        assert_eq!(notifications.entries[0].notification.message, "Downloading 90%");
+        // The replaced toast moves to the top of the stack
+        assert_eq!(notifications.toasts.last().map(|toast| toast.id), Some(0));
+    }
+
+    #[test]
+    fn closed_by_the_app_stays_in_the_list() {
+        let mut notifications = configured();
+
+        let _ = notifications.notify(desktop(3, false, "Build finished"));
+        let _ = notifications.update(Message::Server(notification_server::Event::Close(3)));
+
+        assert_eq!(notifications.toasts_shown(), 0);
+        assert_eq!(notifications.entries.len(), 1);
+    }
+
+    #[test]
+    fn toast_expiry_by_level() {
+        let mut notifications = configured();
+        for level in [Level::Notice, Level::Warning, Level::Error] {
+            notifications.notify_bar(Notification::new(level, "test", Local::now()));
+        }
+
+        let _ = notifications.expire_toasts();
+        assert_eq!(notifications.toasts_shown(), 3);
+
+        // Errors stay after the others expired
+        let later = Instant::now() + Duration::from_secs(11);
+        assert!(notifications.toasts.iter().map(|toast| toast.countdown.expired(later)).eq([true, true, false]));
+    }
+
+    #[test]
+    fn dnd_hides_toasts_but_lists() {
+        let mut notifications = configured();
+        let _ = notifications.update(Message::SetDnd(true));
+
+        notifications.notify_bar(Notification::new(Level::Warning, "test", Local::now()));
+        notifications.notify_bar(Notification::new(Level::Error, "test", Local::now()));
+        let _ = notifications.notify(DesktopNotification { urgency: Urgency::Critical, ..desktop(1, false, "Battery low") });
+
+        assert_eq!(notifications.entries.len(), 3);
+        // Critical notifications of apps are allowed by default, the errors of the bar aren't
+        assert_eq!(notifications.toasts_shown(), 1);
+    }
+
+    #[test]
+    fn manual_dnd_lasts_until_the_schedule_changes() {
+        let time = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+        let mut dnd = Dnd::default();
+        dnd.config.schedules = vec![crate::config::Schedule { start: time(22), end: time(7) }];
+
+        dnd.check_schedules(time(23));
+        assert_eq!(dnd.mode(), DndMode::Scheduled);
+
+        dnd.toggle();
+        dnd.check_schedules(time(1));
+        assert_eq!(dnd.mode(), DndMode::Off);
+
+        dnd.check_schedules(time(8));
+        dnd.set_fullscreen(true);
+        assert_eq!(dnd.mode(), DndMode::Fullscreen);
+    }
+
+    #[test]
+    fn same_notifications_are_counted() {
+        let mut notifications = configured();
+        for _ in 0..3 {
+            notifications.notify_bar(Notification::new(Level::Error, "Weather is unavailable", Local::now()));
+        }
+        notifications.notify_bar(Notification::new(Level::Warning, "Weather is unavailable", Local::now()));
+
+        assert_eq!(notifications.entries.len(), 2);
+        assert_eq!(notifications.entries[0].count, 3);
+        assert!(notifications.entries[0].last_seen >= notifications.entries[0].notification.time);
+    }
+
+    #[test]
+    fn repeats_after_a_reload_are_saved() {
+        let path = std::env::temp_dir().join(format!("rusty-bar-{}-repeats.jsonl", std::process::id()));
+        let error = || Notification::new(Level::Error, "Weather is unavailable", Local::now());
+
+        // Saved by a previous run
+        notification_history::append(&path, &error());
+
+        let mut notifications = configured();
+        notifications.history_days = 7;
+        notifications.history = Some(path.clone());
+        notifications.load_history(&path);
+        notifications.notify_bar(error());
+        notifications.notify_bar(error());
+
+        let records = notification_history::read(&path, None).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(notifications.entries.len(), 1);
+        assert_eq!(notifications.entries[0].count, 3);
+    }
+
+    #[test]
+    fn no_toast_for_a_dropped_entry() {
+        let mut notifications = configured();
+        notifications.max_entries = 0;
+
+        notifications.notify_bar(Notification::new(Level::Notice, "test", Local::now()));
+        assert_eq!(notifications.toasts_shown(), 0);
+    }
+
+    #[test]
+    fn oldest_read_dropped_past_the_limit() {
+        let mut notifications = configured();
+        notifications.max_entries = 3;
+        for message in ["a", "b", "c"] {
+            notifications.push(Notification::new(Level::Notice, message, Local::now()));
+        }
+        notifications.entries[1].read = true;
+
+        notifications.push(Notification::new(Level::Notice, "d", Local::now()));
+        let messages: Vec<&str> = notifications.entries.iter().map(|entry| entry.notification.message.as_str()).collect();
+        assert_eq!(messages, vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn toasts_rate_limited_per_source() {
+        let mut limit = RateLimit { per_minute: 2, ..RateLimit::default() };
+        let now = Instant::now();
+
+        assert!(limit.allow("mail", now) && limit.allow("mail", now));
+        assert!(!limit.allow("mail", now + Duration::from_secs(30)));
+        assert!(limit.allow("rusty-bar", now));
+        assert!(limit.allow("mail", now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn hovered_toast_keeps_its_time() {
+        let countdown = Countdown::new(Some(Duration::from_secs(5))).pause();
+        let Countdown::Paused(left) = countdown else {
+            panic!("a running countdown is paused");
+        };
+        assert!(left > Duration::from_secs(4) && left <= Duration::from_secs(5));
+
+        assert!(!countdown.expired(Instant::now() + Duration::from_secs(60)));
+        assert!(matches!(countdown.resume(), Countdown::Running(_)));
+        assert_eq!(Countdown::Sticky.pause(), Countdown::Sticky);
+    }
+}
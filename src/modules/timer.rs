@@ -0,0 +1,518 @@
+//! A countdown, a stopwatch and pomodoro cycles of work and breaks.
+//! The start of the running one is saved under `$XDG_STATE_HOME/rusty-bar/`, so it keeps counting through a restart of the bar
+
+use std::{io, path::PathBuf, time::Duration};
+
+use chrono::{Local, Utc};
+use iced::{
+    Alignment, Element, Length, Padding, Subscription, Task, Theme, border,
+    mouse::ScrollDelta,
+    widget::{column, container, mouse_area, row, text, text::LineHeight}
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::{BarModule, Context, Scroll};
+use crate::{
+    config::{Config, TimerConfig},
+    logging,
+    notification::{self, Notification}
+};
+
+/// The longest countdown scrolling can set, in minutes
+const MAX_COUNTDOWN: u32 = 24 * 60;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Tick,
+    /// Starts or pauses
+    Toggle,
+    Reset,
+    /// Goes to the next mode, from the countdown to the stopwatch to pomodoro
+    NextMode,
+    /// Ends the current pomodoro phase early
+    Skip,
+    /// Changes the duration of the stopped countdown, a minute per step
+    Scrolled(ScrollDelta),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    #[default]
+    Countdown,
+    Stopwatch,
+    Pomodoro,
+}
+
+impl Mode {
+    fn next(self) -> Self {
+        match self {
+            Mode::Countdown => Mode::Stopwatch,
+            Mode::Stopwatch => Mode::Pomodoro,
+            Mode::Pomodoro => Mode::Countdown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Work => "Work",
+            Phase::ShortBreak => "Short break",
+            Phase::LongBreak => "Long break",
+        }
+    }
+}
+
+/// The end of the countdown or of a pomodoro phase
+#[derive(Debug, PartialEq)]
+enum Finished {
+    /// The countdown of this many minutes
+    Countdown(i64),
+    Phase { ended: Phase, next: Phase },
+}
+
+impl Finished {
+    fn message(&self) -> String {
+        match self {
+            Finished::Countdown(minutes) => format!("The {minutes} min timer is done"),
+            Finished::Phase { ended: Phase::Work, next } => format!("Work is done, time for a {}", next.name().to_lowercase()),
+            Finished::Phase { .. } => String::from("The break is over, back to work"),
+        }
+    }
+}
+
+/// Everything that is saved to the state file, times are unix timestamps in seconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct State {
+    mode: Mode,
+    /// When it was last started, None while it's stopped or paused
+    started: Option<i64>,
+    /// The seconds counted before the last pause
+    counted: i64,
+    /// The duration of the countdown in minutes, if it was changed by scrolling
+    countdown: Option<u32>,
+    phase: Phase,
+    /// Work phases done since the last long break
+    pomodoros: u32,
+}
+
+impl State {
+    fn running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// The seconds counted so far, in the current phase for pomodoro
+    fn elapsed(&self, now: i64) -> i64 {
+        self.counted + self.started.map_or(0, |started| (now - started).max(0))
+    }
+
+    /// The seconds the countdown or the current phase lasts, None for the stopwatch
+    fn duration(&self, config: &TimerConfig) -> Option<i64> {
+        let minutes = match self.mode {
+            Mode::Countdown => self.countdown.unwrap_or(config.countdown),
+            Mode::Stopwatch => return None,
+            Mode::Pomodoro => match self.phase {
+                Phase::Work => config.work,
+                Phase::ShortBreak => config.short_break,
+                Phase::LongBreak => config.long_break,
+            }
+        };
+
+        Some(i64::from(minutes) * 60)
+    }
+
+    /// The time shown, what is left of the countdown and the phases, or the time counted by the stopwatch
+    fn shown(&self, now: i64, config: &TimerConfig) -> i64 {
+        match self.duration(config) {
+            Some(duration) => (duration - self.elapsed(now)).max(0),
+            None => self.elapsed(now)
+        }
+    }
+
+    fn toggle(&mut self, now: i64) {
+        match self.started {
+            Some(_) => {
+                self.counted = self.elapsed(now);
+                self.started = None;
+            },
+            None => self.started = Some(now)
+        }
+    }
+
+    /// Stops, and goes back to the first work phase of pomodoro
+    fn reset(&mut self) {
+        self.started = None;
+        self.counted = 0;
+        self.phase = Phase::Work;
+        self.pomodoros = 0;
+    }
+
+    /// Moves to the phase after the current one, counting the work phases for the long break
+    fn next_phase(&mut self, config: &TimerConfig) {
+        self.phase = match self.phase {
+            Phase::Work => {
+                self.pomodoros += 1;
+                if self.pomodoros >= config.long_break_after {
+                    self.pomodoros = 0;
+                    Phase::LongBreak
+                } else {
+                    Phase::ShortBreak
+                }
+            },
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+    }
+
+    /// Ends the pomodoro phase now, the next one runs if this one was running
+    fn skip(&mut self, now: i64, config: &TimerConfig) {
+        if self.mode != Mode::Pomodoro {
+            return;
+        }
+
+        self.counted = 0;
+        self.started = self.started.map(|_| now);
+        self.next_phase(config);
+    }
+
+    /// Ends the countdown or the pomodoro phases that are over, including the ones that ended while the bar wasn't running.
+    /// Returns the last end
+    fn advance(&mut self, now: i64, config: &TimerConfig) -> Option<Finished> {
+        let mut finished = None;
+
+        while let Some(duration) = self.duration(config) && self.running() && self.elapsed(now) >= duration {
+            match self.mode {
+                Mode::Countdown => {
+                    finished = Some(Finished::Countdown(duration / 60));
+                    self.started = None;
+                    self.counted = 0;
+                },
+                Mode::Pomodoro => {
+                    // The next phase started when this one ended, not on this tick
+                    let overrun = self.elapsed(now) - duration;
+                    self.counted = 0;
+                    self.started = Some(now - overrun);
+
+                    let ended = self.phase;
+                    self.next_phase(config);
+                    finished = Some(Finished::Phase { ended, next: self.phase });
+                },
+                Mode::Stopwatch => break,
+            }
+        }
+
+        finished
+    }
+
+    /// Changes the countdown by `minutes`, only while it's stopped at its full duration
+    fn adjust_countdown(&mut self, minutes: i32, config: &TimerConfig) {
+        if self.mode != Mode::Countdown || self.running() || self.counted != 0 {
+            return;
+        }
+
+        let current = self.countdown.unwrap_or(config.countdown) as i32;
+        self.countdown = Some((current + minutes).clamp(1, MAX_COUNTDOWN as i32) as u32);
+    }
+}
+
+/// `H:MM:SS`, or `MM:SS` under an hour
+fn format_seconds(seconds: i64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
+
+fn state_path() -> PathBuf {
+    logging::state_dir().join("timer.json")
+}
+
+/// The saved state, None if there is none or it can't be read
+fn load() -> Option<State> {
+    let path = state_path();
+
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!("Failed to read the timer state from {}: {e}", path.display());
+            return None;
+        }
+    };
+
+    serde_json::from_str(&source)
+        .inspect_err(|e| warn!("Invalid timer state in {}: {e}", path.display()))
+        .ok()
+}
+
+fn save(state: &State) {
+    let path = state_path();
+
+    let result = serde_json::to_string(state)
+        .map_err(io::Error::other)
+        .and_then(|json| {
+            std::fs::create_dir_all(logging::state_dir())?;
+            std::fs::write(&path, json)
+        });
+
+    if let Err(e) = result {
+        warn!("Failed to save the timer state to {}: {e}", path.display());
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Timer {
+    config: TimerConfig,
+    state: State,
+    /// The state file is only read on startup, not on config reloads
+    loaded: bool,
+    /// The remaining or counted time, formatted
+    text: String,
+    scroll: Scroll,
+}
+
+impl Timer {
+    /// Updates the text, and notifies the end of the countdown or of a phase
+    fn tick(&mut self) -> Task<crate::Message> {
+        let now = Utc::now().timestamp();
+        let finished = self.state.advance(now, &self.config);
+        self.text = format_seconds(self.state.shown(now, &self.config));
+
+        let Some(finished) = finished else {
+            return Task::none();
+        };
+
+        debug!(?finished, "Timer finished");
+        save(&self.state);
+
+        Task::done(
+            crate::Message::NewNotif(
+                Notification::new(notification::Level::Notice, finished.message(), Local::now())
+            )
+        )
+    }
+
+    fn label(&self) -> &'static str {
+        match self.state.mode {
+            Mode::Countdown => "Timer",
+            Mode::Stopwatch => "Stopwatch",
+            Mode::Pomodoro => self.state.phase.name(),
+        }
+    }
+}
+
+impl BarModule for Timer {
+    type Message = Message;
+
+    fn configure(&mut self, config: &Config) -> Task<crate::Message> {
+        self.config = config.timer.clone();
+
+        if !self.loaded {
+            self.loaded = true;
+            if let Some(state) = load() {
+                debug!(?state, "Restored the timer");
+                self.state = state;
+            }
+        }
+
+        self.tick()
+    }
+
+    fn update(&mut self, message: Message) -> Task<crate::Message> {
+        let now = Utc::now().timestamp();
+
+        match message {
+            Message::Tick => return self.tick(),
+            Message::Toggle => self.state.toggle(now),
+            Message::Reset => self.state.reset(),
+            Message::NextMode => {
+                self.state.mode = self.state.mode.next();
+                self.state.reset();
+            },
+            Message::Skip => self.state.skip(now, &self.config),
+            Message::Scrolled(delta) => {
+                let steps = self.scroll.steps(delta);
+                if steps == 0 {
+                    return Task::none();
+                }
+                self.state.adjust_countdown(steps, &self.config);
+            }
+        }
+
+        save(&self.state);
+        self.tick()
+    }
+
+    fn view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message> {
+        let running = self.state.running();
+        let time_style = move |theme: &Theme| {
+            if running {
+                text::primary(theme)
+            } else {
+                text::secondary(theme)
+            }
+        };
+
+        // Vertical bars stack the label over the time
+        let (content, padding, width, height): (Element<'a, crate::Message>, _, _, _) = if ctx.is_vertical() {
+            (
+                column![
+                    text(self.label()).size(11).center().width(Length::Fill).style(text::secondary),
+                    text(&self.text).size(16).center().width(Length::Fill).style(time_style)
+                ]
+                .into(),
+                Padding::default().vertical(ctx.hpadding),
+                Length::Fill,
+                Length::Shrink
+            )
+        } else {
+            (
+                row![
+                    text(self.label()).size(14).style(text::secondary),
+                    text(&self.text).size(24).line_height(LineHeight::Relative(1.0)).style(time_style)
+                ]
+                .spacing(ctx.spacing * 2)
+                .align_y(Alignment::Center)
+                .into(),
+                Padding::default().horizontal(ctx.hpadding),
+                Length::Shrink,
+                Length::Fill
+            )
+        };
+
+        let content = container(content)
+            .padding(padding)
+            .width(width)
+            .height(height)
+            .align_x(Alignment::Center)
+            .align_y(Alignment::Center)
+            .style(|theme: &Theme| {
+                let palette = theme.extended_palette();
+
+                container::Style::default()
+                    .background(palette.background.weak.color)
+                    .border(border::rounded(ctx.radius))
+            });
+
+        mouse_area(content)
+            .on_press(Message::Toggle.into())
+            .on_right_press(Message::Reset.into())
+            .on_middle_press(Message::NextMode.into())
+            .on_scroll(|delta| Message::Scrolled(delta).into())
+            .into()
+    }
+
+    fn subscription(&self) -> Subscription<crate::Message> {
+        // Nothing changes while it's stopped
+        if !self.state.running() {
+            return Subscription::none();
+        }
+
+        iced::time::every(Duration::from_secs(1)).map(|_| Message::Tick.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn running(mode: Mode, started: i64) -> State {
+        State { mode, started: Some(started), ..State::default() }
+    }
+
+    #[test]
+    fn pause_keeps_the_counted_time() {
+        let config = TimerConfig::default();
+        let mut state = running(Mode::Countdown, 1000);
+
+        state.toggle(1060);
+        assert_eq!(state.shown(5000, &config), 9 * 60);
+
+        state.toggle(6000);
+        assert_eq!(state.shown(6030, &config), 9 * 60 - 30);
+    }
+
+    #[test]
+    fn countdown_finishes_once() {
+        let config = TimerConfig::default();
+        let mut state = running(Mode::Countdown, 0);
+
+        assert_eq!(state.advance(599, &config), None);
+        assert_eq!(state.advance(600, &config), Some(Finished::Countdown(10)));
+        assert!(!state.running());
+        assert_eq!(state.advance(700, &config), None);
+        assert_eq!(state.shown(700, &config), 600);
+    }
+
+    #[test]
+    fn stopwatch_counts_up() {
+        let config = TimerConfig::default();
+        let mut state = running(Mode::Stopwatch, 0);
+
+        assert_eq!(state.advance(100_000, &config), None);
+        assert_eq!(state.shown(100_000, &config), 100_000);
+        assert_eq!(format_seconds(state.shown(100_000, &config)), "27:46:40");
+    }
+
+    #[test]
+    fn pomodoro_cycle() {
+        let config = TimerConfig { long_break_after: 2, ..TimerConfig::default() };
+        let mut state = running(Mode::Pomodoro, 0);
+
+        assert_eq!(state.advance(25 * 60, &config), Some(Finished::Phase { ended: Phase::Work, next: Phase::ShortBreak }));
+        assert_eq!(state.advance(30 * 60, &config), Some(Finished::Phase { ended: Phase::ShortBreak, next: Phase::Work }));
+        assert_eq!(state.advance(55 * 60, &config), Some(Finished::Phase { ended: Phase::Work, next: Phase::LongBreak }));
+        assert_eq!(state.shown(60 * 60, &config), 10 * 60);
+    }
+
+    #[test]
+    fn phases_missed_while_not_running() {
+        let config = TimerConfig::default();
+        let mut state = running(Mode::Pomodoro, 0);
+
+        // Restarted 40 minutes later, 10 minutes into the second work phase
+        let finished = state.advance(40 * 60, &config);
+
+        assert_eq!(finished, Some(Finished::Phase { ended: Phase::ShortBreak, next: Phase::Work }));
+        assert_eq!(state.phase, Phase::Work);
+        assert_eq!(state.pomodoros, 1);
+        assert_eq!(state.shown(40 * 60, &config), 15 * 60);
+    }
+
+    #[test]
+    fn scrolling_only_changes_the_stopped_countdown() {
+        let config = TimerConfig::default();
+        let mut state = State::default();
+
+        state.adjust_countdown(-20, &config);
+        assert_eq!(state.duration(&config), Some(60));
+
+        state.adjust_countdown(4, &config);
+        state.toggle(0);
+        state.adjust_countdown(10, &config);
+        assert_eq!(state.duration(&config), Some(5 * 60));
+    }
+
+    #[test]
+    fn saved_state_roundtrip() {
+        let state = State { phase: Phase::ShortBreak, pomodoros: 3, ..running(Mode::Pomodoro, 1_760_000_000) };
+        let json = serde_json::to_string(&state).unwrap();
+
+        assert_eq!(serde_json::from_str::<State>(&json).unwrap(), state);
+        assert_eq!(serde_json::from_str::<State>("{}").unwrap(), State::default());
+    }
+}
@@ -0,0 +1,330 @@
+use std::time::Duration;
+
+use chrono::Local;
+use iced::{Alignment, Element, Length, Padding, Subscription, Task, Theme, border::{self, Radius}, widget::{button, column, container, mouse_area, row, svg, text}};
+use iced::widget::button::Status;
+
+use tracing::debug;
+
+use super::{BarModule, Context};
+use crate::{
+    assets::get_svg,
+    config::{Config, WeatherConfig},
+    notification::{self, Notification},
+    weather::{CurrentWeather, HourlyWeather, prelude::*},
+    windows::weather_window
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Parses both the current and the hourly weather
+    Parse,
+
+    ParseCurrent,
+    CurrentParsed(Result<CurrentWeather, ParsingError>),
+
+    ParseHourly,
+    HourlyParsed(Result<Vec<HourlyWeather>, ParsingError>),
+
+    Window(weather_window::Message),
+}
+
+impl From<weather_window::Message> for crate::Message {
+    fn from(message: weather_window::Message) -> Self {
+        Message::Window(message).into()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Weather {
+    /// None until the first configure
+    config: Option<WeatherConfig>,
+
+    pub current: Option<CurrentWeather>,
+    pub hourly: Vec<HourlyWeather>,
+
+    window: weather_window::State,
+}
+
+/// Shown in place of a value the api left out
+pub const MISSING: &str = "--";
+
+/// The icon of the weather code, a question mark if the code is missing. Daytime is assumed if it's unknown
+pub fn icon(weather: &HourlyWeather) -> svg::Handle {
+    let svg = match &weather.code {
+        Some(code) => get_svg(if weather.is_day.unwrap_or(true) {"day"} else {"night"}, code.get_svg_name().as_str()),
+        None => get_svg("commons", "question_mark")
+    };
+
+    svg::Handle::from_memory(svg.as_bytes())
+}
+
+/// The fields shown by the bar or the popup that are missing from the weather
+fn missing_fields(weather: &HourlyWeather) -> Vec<&'static str> {
+    [
+        ("temperature", weather.temperature.is_none()),
+        ("apparent temperature", weather.apparent_temperature.is_none()),
+        ("day or night", weather.is_day.is_none()),
+        ("precipitation", weather.precipitation.is_none()),
+        ("weather code", weather.code.is_none()),
+        ("wind", weather.wind.is_none()),
+    ]
+    .into_iter()
+    .filter_map(|(field, missing)| missing.then_some(field))
+    .collect()
+}
+
+/// A warning that the api left out some fields, with a retry to parse the weather again
+fn missing_fields_notification(what: &str, fields: &[&str], retry: Message) -> Task<crate::Message> {
+    if fields.is_empty() {
+        return Task::none();
+    }
+
+    Task::done(
+        crate::Message::NewNotif(
+            Notification::new_with_retry(
+                notification::Level::Warning,
+                format!("The {what} is missing the {}", fields.join(", ")),
+                Local::now(),
+                retry
+            )
+        )
+    )
+}
+
+impl Weather {
+    fn location(&self) -> Option<Coordinates> {
+        self.config.as_ref().and_then(|config| config.location.clone())
+    }
+
+    fn units(&self) -> Units {
+        self.config.as_ref().map(|config| config.units.clone()).unwrap_or_default()
+    }
+}
+
+impl BarModule for Weather {
+    type Message = Message;
+
+    /// Parses the weather again if the location or the units changed
+    fn configure(&mut self, config: &Config) -> Task<crate::Message> {
+        if self.config.as_ref() == Some(&config.weather) {
+            return Task::none();
+        }
+
+        let first = self.config.is_none();
+        self.config = Some(config.weather.clone());
+
+        if first {
+            Task::done(Message::ParseCurrent.into())
+        } else {
+            Task::done(Message::Parse.into())
+        }
+    }
+
+    fn update(&mut self, message: Message) -> Task<crate::Message> {
+        match message {
+            Message::Parse => Task::batch(vec![Task::done(Message::ParseCurrent.into()), Task::done(Message::ParseHourly.into())]),
+
+            Message::ParseCurrent => {
+                debug!("Parsing current weather");
+
+                use argument::Current;
+                Task::perform(get_current(
+                    self.location(),
+                    self.units(),
+                    vec![
+                        Current::Temperature,
+                        Current::IsDay,
+                        Current::ApparentTemp,
+                        Current::Humidity,
+                        Current::WeatherCode,
+                        Current::WindDirection,
+                        Current::WindSpeed,
+                        Current::Precipitation(argument::PrecipitationType::Combined),
+                        Current::Precipitation(argument::PrecipitationType::Rain),
+                        Current::Precipitation(argument::PrecipitationType::Showers),
+                        Current::Precipitation(argument::PrecipitationType::Snowfall)
+                    ]
+                ), |result| Message::CurrentParsed(result).into())
+            },
+            Message::CurrentParsed(result) => {
+                match result {
+                    Ok(weather) => {
+                        let mut missing = missing_fields(&weather);
+                        if weather.humidity.is_none() {
+                            missing.push("humidity");
+                        }
+                        self.current = Some(weather);
+
+                        missing_fields_notification("current weather", &missing, Message::ParseCurrent)
+                    },
+                    Err(e) => {
+                        Task::done(
+                            crate::Message::NewNotif(
+                                Notification::new_with_retry(
+                                    notification::Level::Error,
+                                    e.to_string(),
+                                    Local::now(),
+                                    Message::ParseCurrent
+                                )
+                            )
+                        )
+                    }
+                }
+            },
+
+            Message::ParseHourly => {
+                let hours: u8 = self.config.as_ref().map(|config| config.hours).unwrap_or(24);
+                debug!(hours, "Parsing hourly weather");
+
+                use argument::Hourly;
+                Task::perform(
+                    get_hourly(
+                        self.location(),
+                        self.units(),
+                        vec![
+                            Hourly::WeatherCode,
+                            Hourly::Temperature,
+                            Hourly::ApparentTemp,
+                            Hourly::IsDay,
+                            Hourly::PrecipitationProbability,
+                            Hourly::WindSpeed,
+                            Hourly::Precipitation(argument::PrecipitationType::Combined),
+                            Hourly::Precipitation(argument::PrecipitationType::Rain),
+                            Hourly::Precipitation(argument::PrecipitationType::Showers),
+                            Hourly::Precipitation(argument::PrecipitationType::Snowfall)
+                        ],
+                        hours
+                    ),
+                    |result| Message::HourlyParsed(result).into()
+                )
+            },
+            Message::HourlyParsed(result) => {
+                match result {
+                    Ok(result) => {
+                        let mut missing: Vec<&str> = Vec::new();
+                        for field in result.iter().flat_map(missing_fields) {
+                            if !missing.contains(&field) {
+                                missing.push(field);
+                            }
+                        }
+                        self.hourly = result;
+
+                        missing_fields_notification("hourly weather", &missing, Message::ParseHourly)
+                    },
+                    Err(e) => {
+                        Task::done(
+                            crate::Message::NewNotif(
+                                Notification::new_with_retry(
+                                    notification::Level::Error,
+                                    e,
+                                    Local::now(),
+                                    Message::ParseHourly
+                                )
+                            )
+                        )
+                    }
+                }
+            },
+
+            Message::Window(message) => self.window.update(message),
+        }
+    }
+
+    fn view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message> {
+        // Vertical bars show the icon above a smaller temperature, and fill the bar's width instead of its height
+        let (padding, width, height) = if ctx.is_vertical() {
+            (Padding::default().vertical(ctx.hpadding), Length::Fill, Length::Shrink)
+        } else {
+            (Padding::default().horizontal(ctx.hpadding), Length::Shrink, Length::Fill)
+        };
+
+        match &self.current {
+            Some(weather) => {
+                let svg_handle = icon(weather);
+
+                let temperature = weather.temperature
+                    .as_ref()
+                    .map(|temperature| temperature.stringify())
+                    .unwrap_or_else(|| MISSING.to_string());
+
+                let content: Element<'a, crate::Message> = if ctx.is_vertical() {
+                    column!
+                    [
+                        svg(svg_handle)
+                            .width(28)
+                            .height(28)
+                            .content_fit(iced::ContentFit::Fill),
+                        text(temperature)
+                            .size(16)
+                            .style(text::primary)
+                    ]
+                    .spacing(2)
+                    .align_x(Alignment::Center)
+                    .into()
+                } else {
+                    row!
+                    [
+                        svg(svg_handle)
+                            .width(36)
+                            .height(36)
+                            .content_fit(iced::ContentFit::Fill),
+                        text(temperature)
+                            .align_y(Alignment::Center)
+                            .size(36)
+                            .style(text::primary)
+                    ]
+                    .spacing(5)
+                    .align_y(Alignment::Center)
+                    .into()
+                };
+
+                container
+                (
+                    mouse_area(content)
+                        .on_press(crate::Message::WeatherWindowToggle(None))
+                )
+                .padding(padding)
+                .width(width)
+                .height(height)
+                .align_x(Alignment::Center)
+                .style(|theme: &Theme| {
+                    let palette = theme.extended_palette();
+
+                    container::Style::default()
+                        .background(palette.background.weak.color)
+                        .border(border::rounded(ctx.radius))
+                })
+                .into()
+            },
+            None => {
+                button
+                (
+                        svg(
+                            svg::Handle::from_memory(get_svg("commons", "question_mark").as_bytes())
+                        )
+                        .width(36)
+                        .height(36)
+                )
+                .on_press(Message::ParseCurrent.into())
+                .style(|theme: &Theme, state: Status| button::Style {
+                    background: Some(if state == Status::Hovered {theme.extended_palette().background.stronger.color} else {theme.extended_palette().background.weak.color}.into()),
+                    border: iced::Border { radius: Radius::new(ctx.radius as f32), ..Default::default() },
+                    ..Default::default()
+                })
+                .padding(padding)
+                .width(width)
+                .height(height)
+                .into()
+            }
+        }
+    }
+
+    fn subscription(&self) -> Subscription<crate::Message> {
+        iced::time::every(Duration::from_mins(15)).map(|_| Message::ParseCurrent.into())
+    }
+
+    fn popup_view<'a>(&'a self, ctx: &'a Context) -> Option<Element<'a, crate::Message>> {
+        Some(self.window.view(self, ctx))
+    }
+}
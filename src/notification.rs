@@ -4,6 +4,7 @@ use std::fmt::Display;
 
 use chrono::{DateTime, Local};
 use iced::Task;
+use serde::{Deserialize, Serialize};
 
 use crate::Message;
 
@@ -12,7 +13,25 @@ pub struct Notification {
     pub level: Level,
     pub message: String,
     pub time: DateTime<Local>,
-    retry_message: Option<&'static Message>
+    pub origin: Origin,
+    retry_message: Option<Box<Message>>
+}
+
+/// Where a notification comes from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Origin {
+    /// Rusty bar itself
+    #[default]
+    Bar,
+    /// A desktop app, through the notification server. The message is the summary of the notification
+    App {
+        name: String,
+        /// The id the app knows the notification by
+        id: u32,
+        body: String,
+        /// The key and the label of every action, shown as buttons
+        actions: Vec<(String, String)>,
+    }
 }
 
 impl Display for Notification {
@@ -27,30 +46,71 @@ impl Notification {
             level, 
             message: message.to_string(), 
             time, 
+            origin: Origin::Bar,
             retry_message: None
         }
     }
 
-    pub fn new_with_retry<T: ToString>(level: Level, message: T, time: DateTime<Local>, retry_message: &'static Message) -> Self {
+    pub fn new_with_retry<T: ToString>(level: Level, message: T, time: DateTime<Local>, retry_message: impl Into<Message>) -> Self {
         Self { 
             level, 
             message: message.to_string(), 
             time, 
-            retry_message: Some(retry_message)
+            origin: Origin::Bar,
+            retry_message: Some(Box::new(retry_message.into()))
         }
     }
 
+    pub fn with_origin(self, origin: Origin) -> Self {
+        Self { origin, ..self }
+    }
+
+    /// The app the notification comes from, or rusty bar
+    pub fn source(&self) -> &str {
+        match &self.origin {
+            Origin::App { name, .. } => name,
+            Origin::Bar => "rusty-bar"
+        }
+    }
+
+    /// The same notification again, from the same source. The time, the id of the app and the actions don't count
+    pub fn same_as(&self, other: &Self) -> bool {
+        let same_origin = match (&self.origin, &other.origin) {
+            (Origin::Bar, Origin::Bar) => true,
+            (Origin::App { name, body, .. }, Origin::App { name: other_name, body: other_body, .. }) => name == other_name && body == other_body,
+            _ => false
+        };
+
+        same_origin && self.level == other.level && self.message == other.message
+    }
+
+    pub fn retryable(&self) -> bool {
+        self.retry_message.is_some()
+    }
+
     pub fn retry(&self) -> Result<Task<Message>, String> {
-        match self.retry_message {
-            Some(message) => Ok(Task::done(message.clone())),
+        match &self.retry_message {
+            Some(message) => Ok(Task::done(message.as_ref().clone())),
             None => Err(format!("retry called on a non retryable notif:\n{} {:?}:\n{}", &self.time.format("%Y/%m/%d %H:%M:%S"), &self.level, &self))
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Ordered by severity, from Notice to Error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Level {
     Notice,
     Warning,
     Error
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Notice => "Notice",
+            Level::Warning => "Warning",
+            Level::Error => "Error",
+        }
+    }
 }
\ No newline at end of file
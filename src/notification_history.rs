@@ -0,0 +1,219 @@
+//! The history of the notifications, one JSON record per line in `$XDG_STATE_HOME/rusty-bar/notifications.jsonl`.
+//! Records are appended as notifications come, and the ones older than the retention are dropped on startup and once a day
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::Duration
+};
+
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{logging, notification::{Level, Notification, Origin}};
+
+/// A notification as it's saved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub level: Level,
+    pub message: String,
+    #[serde(with = "rfc3339")]
+    pub time: DateTime<Local>,
+    /// The app that sent the notification, None for the notifications of the bar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+}
+
+impl From<&Notification> for Record {
+    fn from(notification: &Notification) -> Self {
+        let (app, body) = match &notification.origin {
+            Origin::App { name, body, .. } => (Some(name.clone()), body.clone()),
+            Origin::Bar => (None, String::new())
+        };
+
+        Self {
+            level: notification.level,
+            message: notification.message.clone(),
+            time: notification.time,
+            app,
+            body,
+        }
+    }
+}
+
+impl From<Record> for Notification {
+    /// The actions and the retry aren't saved, so reloaded notifications have neither
+    fn from(record: Record) -> Self {
+        let notification = Notification::new(record.level, record.message, record.time);
+
+        match record.app {
+            Some(name) => notification.with_origin(Origin::App { name, id: 0, body: record.body, actions: Vec::new() }),
+            None => notification
+        }
+    }
+}
+
+/// Times are saved as RFC 3339, so the file can be read without rusty bar
+mod rfc3339 {
+    use chrono::{DateTime, Local};
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(time: &DateTime<Local>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.to_rfc3339())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Local>, D::Error> {
+        let time = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&time)
+            .map(|time| time.with_timezone(&Local))
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Held while the file is written, a record appended while the file is rewritten would be lost
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// Where the history is saved
+pub fn path() -> PathBuf {
+    logging::state_dir().join("notifications.jsonl")
+}
+
+/// The records of the lines, invalid lines are skipped
+fn parse(source: &str) -> Vec<Record> {
+    let mut invalid = 0;
+    let records = source
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).inspect_err(|_| invalid += 1).ok())
+        .collect();
+
+    if invalid > 0 {
+        warn!("Skipped {invalid} invalid lines of the notification history");
+    }
+    records
+}
+
+/// The records of the history newer than `age`, or every record without one. The oldest first
+pub fn read(path: &Path, age: Option<Duration>) -> io::Result<Vec<Record>> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e)
+    };
+
+    let mut records = parse(&source);
+    // Ages older than chrono can represent keep every record
+    let cutoff = age
+        .and_then(|age| TimeDelta::from_std(age).ok())
+        .and_then(|age| Local::now().checked_sub_signed(age));
+    if let Some(cutoff) = cutoff {
+        records.retain(|record| record.time >= cutoff);
+    }
+    Ok(records)
+}
+
+/// Loads the history of the last `days` days, and drops the older records from the file
+pub fn load(path: &Path, days: u32) -> Vec<Record> {
+    let _writing = WRITING.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let all = match read(path, None) {
+        Ok(records) => records,
+        Err(e) => {
+            warn!("Failed to read the notification history from {}: {e}", path.display());
+            return Vec::new();
+        }
+    };
+
+    let cutoff = Local::now() - TimeDelta::days(days.into());
+    let records: Vec<Record> = all.iter().filter(|record| record.time >= cutoff).cloned().collect();
+
+    if records.len() < all.len()
+        && let Err(e) = rewrite(path, &records)
+    {
+        warn!("Failed to prune the notification history in {}: {e}", path.display());
+    }
+
+    records
+}
+
+/// Drops the records older than `days` days from the file, which grows for as long as the bar runs
+pub fn prune(path: &Path, days: u32) {
+    load(path, days);
+}
+
+fn rewrite(path: &Path, records: &[Record]) -> io::Result<()> {
+    let mut lines = String::new();
+    for record in records {
+        lines.push_str(&serde_json::to_string(record).map_err(io::Error::other)?);
+        lines.push('\n');
+    }
+
+    fs::write(path, lines)
+}
+
+/// Appends a notification to the history
+pub fn append(path: &Path, notification: &Notification) {
+    let _writing = WRITING.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let result = serde_json::to_string(&Record::from(notification))
+        .map_err(io::Error::other)
+        .and_then(|json| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{json}")
+        });
+
+    if let Err(e) = result {
+        warn!("Failed to save the notification to {}: {e}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_roundtrip() {
+        let notification = Notification::new(Level::Warning, "Weather is unavailable", Local::now());
+        let json = serde_json::to_string(&Record::from(&notification)).unwrap();
+
+        assert!(json.starts_with(r#"{"level":"warning","message":"Weather is unavailable","time":""#));
+        assert_eq!(parse(&json), vec![Record::from(&notification)]);
+    }
+
+    #[test]
+    fn invalid_lines_skipped() {
+        let source = "\
+{\"level\":\"error\",\"message\":\"a\",\"time\":\"2026-10-01T10:00:00+02:00\",\"app\":\"mail\"}
+not json
+
+{\"level\":\"notice\",\"message\":\"b\",\"time\":\"yesterday\"}
+";
+        let records = parse(source);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].app.as_deref(), Some("mail"));
+    }
+
+    #[test]
+    fn prune_drops_old_records() {
+        let path = std::env::temp_dir().join(format!("rusty-bar-prune-{}.jsonl", std::process::id()));
+        let old = Notification::new(Level::Notice, "old", Local::now() - TimeDelta::days(10));
+        let new = Notification::new(Level::Notice, "new", Local::now());
+        append(&path, &old);
+        append(&path, &new);
+
+        prune(&path, 7);
+        let records = read(&path, None);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(records.unwrap(), vec![Record::from(&new)]);
+    }
+}
@@ -0,0 +1,285 @@
+//! A server of the `org.freedesktop.Notifications` interface, so desktop apps send their notifications to the bar.
+//! Only one notification server can own the name on the session bus, it fails to start if another daemon (like mako or dunst) runs
+
+use std::{collections::HashMap, sync::atomic::{AtomicU32, Ordering}};
+
+use futures::{SinkExt, Stream, StreamExt, channel::mpsc};
+use iced::stream;
+use tracing::{info, warn};
+use zbus::{interface, object_server::SignalEmitter, zvariant::{OwnedValue, Value}};
+
+const NAME: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+
+/// The urgency hint of a notification
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+impl From<u8> for Urgency {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Low,
+            2 => Self::Critical,
+            _ => Self::Normal
+        }
+    }
+}
+
+/// How long a notification is shown, as asked by the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    /// The timeout of the config
+    Default,
+    Never,
+    Milliseconds(u32),
+}
+
+impl From<i32> for Expiry {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => Self::Never,
+            timeout if timeout > 0 => Self::Milliseconds(timeout.unsigned_abs()),
+            _ => Self::Default
+        }
+    }
+}
+
+/// The reason of the NotificationClosed signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    /// Closed with CloseNotification
+    Closed = 3,
+}
+
+/// A notification sent by an app with Notify
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesktopNotification {
+    pub id: u32,
+    /// The notification this one replaces, with the same id
+    pub replaces: bool,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    /// The key and the label of every action
+    pub actions: Vec<(String, String)>,
+    pub urgency: Urgency,
+    pub expiry: Expiry,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The server owns the name on the bus, signals are emitted through the handle
+    Started(Handle),
+    Notify(DesktopNotification),
+    /// An app closed one of its notifications with CloseNotification
+    Close(u32),
+}
+
+/// Emits the signals of the server
+#[derive(Debug, Clone)]
+pub struct Handle(zbus::Connection);
+
+impl Handle {
+    pub async fn closed(self, id: u32, reason: CloseReason) {
+        let result = async {
+            let emitter = SignalEmitter::new(&self.0, PATH)?;
+            Server::notification_closed(&emitter, id, reason as u32).await
+        }.await;
+
+        if let Err(e) = result {
+            warn!(id, "Failed to emit NotificationClosed: {e}");
+        }
+    }
+
+    pub async fn action_invoked(self, id: u32, key: String) {
+        let result = async {
+            let emitter = SignalEmitter::new(&self.0, PATH)?;
+            Server::action_invoked(&emitter, id, &key).await
+        }.await;
+
+        if let Err(e) = result {
+            warn!(id, key, "Failed to emit ActionInvoked: {e}");
+        }
+    }
+}
+
+/// Ids start at 1, 0 means no notification is replaced.
+/// Kept across restarts of the server, so the ids of the notifications listed before aren't given out again
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Whether the id was given out to a notification
+fn known(id: u32) -> bool {
+    id != 0 && id < NEXT_ID.load(Ordering::Relaxed)
+}
+
+struct Server {
+    events: mpsc::UnboundedSender<Event>,
+}
+
+/// The actions come as a flat list of keys and labels
+fn pair_actions(actions: Vec<String>) -> Vec<(String, String)> {
+    actions
+        .chunks_exact(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect()
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl Server {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: String,
+        replaces_id: u32,
+        _app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+    ) -> u32 {
+        let id = match replaces_id {
+            0 => NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            id => id
+        };
+
+        let urgency = match hints.get("urgency").map(|value| &**value) {
+            Some(Value::U8(urgency)) => Urgency::from(*urgency),
+            _ => Urgency::default()
+        };
+
+        let _ = self.events.unbounded_send(Event::Notify(DesktopNotification {
+            id,
+            replaces: replaces_id != 0,
+            app_name,
+            summary,
+            body,
+            actions: pair_actions(actions),
+            urgency,
+            expiry: Expiry::from(expire_timeout),
+        }));
+
+        id
+    }
+
+    /// Apps get the signal for every id given out, even if its toast was already closed or never shown
+    async fn close_notification(&self, id: u32, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+        if known(id)
+            && let Err(e) = Self::notification_closed(&emitter, id, CloseReason::Closed as u32).await
+        {
+            warn!(id, "Failed to emit NotificationClosed: {e}");
+        }
+
+        let _ = self.events.unbounded_send(Event::Close(id));
+    }
+
+    fn get_capabilities(&self) -> Vec<String> {
+        vec![String::from("body"), String::from("actions")]
+    }
+
+    /// The name, vendor and version of the server, and the version of the specification
+    fn get_server_information(&self) -> (String, String, String, String) {
+        (String::from("rusty-bar"), String::from("rusty-bar"), String::from(env!("CARGO_PKG_VERSION")), String::from("1.2"))
+    }
+
+    #[zbus(signal)]
+    async fn notification_closed(emitter: &SignalEmitter<'_>, id: u32, reason: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn action_invoked(emitter: &SignalEmitter<'_>, id: u32, action_key: &str) -> zbus::Result<()>;
+}
+
+/// Serves the interface on the session bus, and yields its events.
+/// The first event is [Event::Started], nothing is yielded if the name is owned by another server
+pub fn serve() -> impl Stream<Item = Event> {
+    stream::channel(16, async |mut output| {
+        let (sender, mut events) = mpsc::unbounded();
+        let server = Server { events: sender };
+
+        let connection = async {
+            zbus::connection::Builder::session()?
+                .name(NAME)?
+                .serve_at(PATH, server)?
+                .build()
+                .await
+        }.await;
+
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to start the notification server, is another one running? {e}");
+                return;
+            }
+        };
+
+        info!("Serving {NAME}");
+        // The connection stays open as long as the stream runs, the handle is only used for signals
+        let _ = output.send(Event::Started(Handle(connection.clone()))).await;
+
+        while let Some(event) = events.next().await {
+            let _ = output.send(event).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_are_paired() {
+        let actions = ["default", "Open", "reply", "Reply", "dangling"].map(String::from).to_vec();
+
+        assert_eq!(pair_actions(actions), vec![
+            (String::from("default"), String::from("Open")),
+            (String::from("reply"), String::from("Reply")),
+        ]);
+    }
+
+    #[test]
+    fn expire_timeouts() {
+        assert_eq!(Expiry::from(-1), Expiry::Default);
+        assert_eq!(Expiry::from(0), Expiry::Never);
+        assert_eq!(Expiry::from(3000), Expiry::Milliseconds(3000));
+    }
+
+    #[tokio::test]
+    async fn every_close_is_signaled() -> zbus::Result<()> {
+        let (server_socket, client_socket) = tokio::net::UnixStream::pair()?;
+        let (sender, _events) = mpsc::unbounded();
+
+        let (_server, client) = futures::try_join!(
+            zbus::connection::Builder::unix_stream(server_socket)
+                .server(zbus::Guid::generate())?
+                .p2p()
+                .serve_at(PATH, Server { events: sender })?
+                .build(),
+            zbus::connection::Builder::unix_stream(client_socket).p2p().build(),
+        )?;
+
+        let proxy = zbus::Proxy::new(&client, NAME, PATH, NAME).await?;
+        let mut closed = proxy.receive_signal("NotificationClosed").await?;
+
+        let id: u32 = proxy.call("Notify", &(
+            "make", 0u32, "", "Build finished", "", Vec::<String>::new(), HashMap::<String, OwnedValue>::new(), -1
+        )).await?;
+        // Closing an unknown id isn't signaled, closing the same notification again is
+        proxy.call::<_, _, ()>("CloseNotification", &(0u32,)).await?;
+        proxy.call::<_, _, ()>("CloseNotification", &(id,)).await?;
+        proxy.call::<_, _, ()>("CloseNotification", &(id,)).await?;
+
+        for _ in 0..2 {
+            let signal = closed.next().await.expect("the signal is received");
+            assert_eq!(signal.body().deserialize::<(u32, u32)>()?, (id, CloseReason::Closed as u32));
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 #![allow(unused)]
+use serde::Deserialize;
+
 /// Base measurements
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[allow(dead_code)]
 pub enum Speed {
     Kmh, 
@@ -36,7 +39,8 @@ impl Default for Speed {
 }
 
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TempUnit {
     Celsius,
     Fahrenheit
@@ -62,7 +66,8 @@ impl Default for TempUnit {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[allow(dead_code)]
 pub enum Length {
     Mm,
@@ -84,7 +89,8 @@ impl Default for Length {
 }
 
 /// A collection of primitive types
-#[derive(Clone, Debug, PartialEq, Default)]
+#[derive(Clone, Debug, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Units {
     pub speed: Speed,
     pub temperature: TempUnit,
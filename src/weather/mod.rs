@@ -32,6 +32,13 @@ pub mod prelude {
         TempUnit
     };
 
+    pub use units::{
+        Temperature,
+        Humidity,
+        Precipitation,
+        Wind
+    };
+
     pub use parsing::{
         get_current,
         get_hourly,
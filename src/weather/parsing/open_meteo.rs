@@ -93,12 +93,12 @@ impl OpenMeteo {
         url.push_str(format!("&timezone={}", tz).as_str());
 
         // Forecast days
-        if self.forecast_days.is_some() {
-            url.push_str(format!("&forecast_days={}", self.forecast_days.unwrap()).as_str());
+        if let Some(days) = self.forecast_days {
+            url.push_str(format!("&forecast_days={}", days).as_str());
         }
 
-        if self.forecast_days.is_some() {
-            url.push_str(format!("&forecast_hours={}", self.forecast_hours.unwrap()).as_str());
+        if let Some(hours) = self.forecast_hours {
+            url.push_str(format!("&forecast_hours={}", hours).as_str());
         }
 
         // Current
@@ -187,6 +187,22 @@ mod tests {
         assert_eq!(weather_url, correct, "Incorrect url creation on OpenMeteo");
     }
 
+    #[test]
+    fn url_forecast_hours_without_days() {
+        let coordinates = Coordinates::new(50.0, 20.0);
+        let units = Units::new(Speed::Knots, TempUnit::Fahrenheit, Length::Inch);
+
+        let weather_url = OpenMeteo::new(coordinates)
+        .hourly(vec![Hourly::Temperature])
+        .units(units)
+        .forecast_hours(12)
+        .build_url();
+
+        let correct = String::from("latitude=20&longitude=50&timezone=auto&forecast_hours=12&hourly=,temperature_2m&wind_speed_unit=kn&temperature_unit=fahrenheit&precipitation_unit=inch");
+
+        assert_eq!(weather_url, correct, "Incorrect url creation on OpenMeteo");
+    }
+
     #[ignore = "This test should only be run when there is internet connection, and api.open-meteo.com is reachable"]
     #[tokio::test]
     async fn try_parsing() {
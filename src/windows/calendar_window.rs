@@ -0,0 +1,433 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveTime, TimeDelta, TimeZone};
+use iced::{
+    Alignment, Element, Length, Task, Theme, border,
+    mouse::ScrollDelta,
+    widget::{Column, Row, button, button::Status, column, container, mouse_area, row, scrollable, space, text, text::LineHeight}
+};
+
+use crate::{calendar::{self, Event, Occurrence}, modules::{Context, Scroll}};
+
+/// The size of a day in the grid
+const CELL_WIDTH: u32 = 36;
+const CELL_HEIGHT: u32 = 30;
+
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+/// The height of the agenda below the grid
+const AGENDA_HEIGHT: u32 = 150;
+
+/// The size of the popup, fits the week numbers and six weeks, and the agenda of the selected day if there are calendars
+pub fn size(agenda: bool) -> (u32, u32) {
+    let height = 9 * CELL_HEIGHT + 30;
+    (8 * CELL_WIDTH + 20, if agenda { height + AGENDA_HEIGHT } else { height })
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    PreviousMonth,
+    NextMonth,
+    /// Goes back to the current month and selects today
+    Today,
+    /// Shows the events of a day, and goes to its month
+    Select(NaiveDate),
+    /// Scrolling up goes to the previous month, down to the next one
+    Scrolled(ScrollDelta),
+}
+
+#[derive(Debug)]
+pub struct State {
+    /// The first day of the month shown
+    month: NaiveDate,
+    /// The day the agenda is shown for
+    selected: NaiveDate,
+    scroll: Scroll,
+
+    /// Whether there are calendars configured, the agenda is only shown with them
+    calendars: bool,
+    /// The days of the grid with events
+    marked: HashSet<NaiveDate>,
+    /// The events of the selected day
+    agenda: Vec<Occurrence>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            month: first_of_month(today()),
+            selected: today(),
+            scroll: Scroll::default(),
+            calendars: false,
+            marked: HashSet::new(),
+            agenda: Vec::new()
+        }
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// The weeks of the month's grid, each from monday to sunday.
+/// The first week has the first day of the month, so it can start in the previous month, and the last can end in the next month
+fn weeks(month: NaiveDate) -> Vec<[NaiveDate; 7]> {
+    let month = first_of_month(month);
+    let start = month - Days::new(month.weekday().num_days_from_monday().into());
+    let end = month.checked_add_months(Months::new(1)).unwrap_or(NaiveDate::MAX);
+
+    std::iter::successors(Some(start), |monday| monday.checked_add_days(Days::new(7)))
+        .take_while(|monday| *monday < end)
+        .map(|monday| std::array::from_fn(|i| monday.checked_add_days(Days::new(i as u64)).unwrap_or(monday)))
+        .collect()
+}
+
+/// The start of the day in local time, or the first moment after it if midnight is skipped by daylight saving
+fn midnight(date: NaiveDate) -> Option<DateTime<Local>> {
+    let time = date.and_time(NaiveTime::MIN);
+    Local.from_local_datetime(&time).earliest()
+        .or_else(|| Local.from_local_datetime(&(time + TimeDelta::hours(1))).earliest())
+}
+
+/// The days an occurrence is on, the end of all day events is the midnight after their last day
+fn days(occurrence: &Occurrence) -> impl Iterator<Item = NaiveDate> {
+    let first = occurrence.start.date_naive();
+    let last = if occurrence.end > occurrence.start {
+        (occurrence.end - TimeDelta::nanoseconds(1)).date_naive()
+    } else {
+        first
+    };
+
+    first.iter_days().take_while(move |day| *day <= last)
+}
+
+impl State {
+    /// Whether there are calendars configured
+    pub fn configure(&mut self, calendars: bool) {
+        self.calendars = calendars;
+    }
+
+    pub fn update(&mut self, message: Message, events: &[Event]) -> Task<crate::Message> {
+        match message {
+            Message::PreviousMonth => self.shift(-1),
+            Message::NextMonth => self.shift(1),
+            Message::Today => {
+                self.selected = today();
+                self.month = first_of_month(self.selected);
+            },
+            Message::Select(day) => {
+                self.selected = day;
+                self.month = first_of_month(day);
+            },
+            Message::Scrolled(delta) => {
+                let steps = self.scroll.steps(delta);
+                self.shift(-steps);
+            }
+        }
+
+        self.refresh(events);
+        Task::none()
+    }
+
+    /// Marks the days of the grid with events and lists the events of the selected day, called when the events or the month change
+    pub fn refresh(&mut self, events: &[Event]) {
+        self.marked.clear();
+        self.agenda.clear();
+
+        if events.is_empty() {
+            return;
+        }
+
+        let weeks = weeks(self.month);
+        let grid = weeks.first().zip(weeks.last()).and_then(|(first, last)| {
+            Some((midnight(first[0])?, midnight(last[6].succ_opt()?)?))
+        });
+
+        if let Some((from, to)) = grid {
+            self.marked = calendar::occurrences(events, from, to)
+                .iter()
+                .flat_map(days)
+                .collect();
+        }
+
+        if let Some((from, to)) = midnight(self.selected).zip(self.selected.succ_opt().and_then(midnight)) {
+            self.agenda = calendar::occurrences(events, from, to);
+        }
+    }
+
+    /// Moves the shown month by `months`
+    fn shift(&mut self, months: i32) {
+        let shifted = if months < 0 {
+            self.month.checked_sub_months(Months::new(months.unsigned_abs()))
+        } else {
+            self.month.checked_add_months(Months::new(months.unsigned_abs()))
+        };
+
+        if let Some(month) = shifted {
+            self.month = month;
+        }
+    }
+
+    fn day_view<'a>(&self, day: NaiveDate, today: NaiveDate) -> Element<'a, crate::Message> {
+        let is_today = day == today;
+        let in_month = day.month() == self.month.month();
+        let is_selected = self.calendars && day == self.selected;
+        let is_marked = self.marked.contains(&day);
+
+        let number = text(day.day().to_string())
+            .center()
+            .line_height(LineHeight::Relative(1.0))
+            .style(move |theme: &Theme| {
+                let palette = theme.extended_palette();
+
+                text::Style {
+                    color: Some(if is_today {
+                        palette.primary.strong.text
+                    } else if in_month {
+                        palette.background.base.text
+                    } else {
+                        palette.background.strong.color
+                    })
+                }
+            });
+
+        // A dot below the number of days with events
+        let marker = text(if is_marked { "•" } else { " " })
+            .size(8)
+            .line_height(LineHeight::Relative(1.0))
+            .style(move |theme: &Theme| {
+                let palette = theme.extended_palette();
+                text::Style { color: Some(if is_today { palette.primary.strong.text } else { palette.primary.base.color }) }
+            });
+
+        let cell = container(column![number, marker].align_x(Alignment::Center))
+            .center_x(CELL_WIDTH)
+            .center_y(CELL_HEIGHT)
+            .style(move |theme: &Theme| {
+                let palette = theme.extended_palette();
+                let mut style = container::Style::default().border(border::rounded(CELL_HEIGHT as f32 / 2.0));
+
+                if is_today {
+                    style = style.background(palette.primary.strong.color);
+                }
+                // The selected day gets a ring, so it's visible on today too
+                if is_selected {
+                    style.border = style.border.color(palette.primary.base.color).width(2.0);
+                }
+                style
+            });
+
+        if self.calendars {
+            mouse_area(cell).on_press(Message::Select(day).into()).into()
+        } else {
+            cell.into()
+        }
+    }
+
+    /// The events of the selected day
+    fn agenda_view<'a>(&'a self) -> Element<'a, crate::Message> {
+        let title = text(self.selected.format("%A, %-d %B").to_string())
+            .size(14)
+            .style(text::primary);
+
+        if self.agenda.is_empty() {
+            return column![
+                title,
+                text("No events").size(13).style(text::secondary)
+            ]
+            .spacing(4)
+            .into();
+        }
+
+        let events = Column::with_children(
+            self.agenda.iter().map(|occurrence| {
+                let time = if occurrence.all_day {
+                    String::from("All day")
+                } else {
+                    format!("{}–{}", occurrence.start.format("%H:%M"), occurrence.end.format("%H:%M"))
+                };
+
+                let details = match &occurrence.location {
+                    Some(location) => column![
+                        text(&occurrence.summary).size(13),
+                        text(location).size(11).style(text::secondary)
+                    ],
+                    None => column![text(&occurrence.summary).size(13)]
+                };
+
+                row![
+                    text(time).size(13).width(90).style(text::secondary),
+                    details.width(Length::Fill)
+                ]
+                .spacing(6)
+                .into()
+            })
+        )
+        .spacing(4);
+
+        column![
+            title,
+            scrollable(events).height(Length::Fill)
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    pub fn view<'a>(&'a self, ctx: &'a Context) -> Element<'a, crate::Message> {
+        let today = today();
+
+        let nav_button = move |label: &'a str, message: Option<Message>| {
+            button(text(label).center())
+                .height(CELL_HEIGHT)
+                .on_press_maybe(message.map(crate::Message::from))
+                .style(move |theme: &Theme, status: Status| {
+                    let palette = theme.extended_palette();
+
+                    button::Style {
+                        background: Some(match status {
+                            Status::Hovered | Status::Pressed => palette.primary.strong.color,
+                            _ => palette.background.strong.color
+                        }.into()),
+                        text_color: match status {
+                            Status::Disabled => palette.background.strongest.color,
+                            _ => palette.background.base.text
+                        },
+                        border: border::rounded(ctx.radius),
+                        ..Default::default()
+                    }
+                })
+        };
+
+        let is_current = self.month == first_of_month(today);
+        let header = row![
+            nav_button("<", Some(Message::PreviousMonth)).width(CELL_WIDTH),
+            text(self.month.format("%B %Y").to_string())
+                .size(20)
+                .center()
+                .width(Length::Fill)
+                .style(text::primary),
+            nav_button("Today", (!is_current || self.selected != today).then_some(Message::Today)),
+            nav_button(">", Some(Message::NextMonth)).width(CELL_WIDTH),
+        ]
+        .spacing(4)
+        .align_y(Alignment::Center);
+
+        let dimmed = |theme: &Theme| text::Style { color: Some(theme.extended_palette().background.strong.color) };
+
+        let weekdays = Row::with_children(
+            std::iter::once("Wk").chain(WEEKDAYS).map(|name| {
+                text(name)
+                    .size(13)
+                    .center()
+                    .width(CELL_WIDTH)
+                    .style(move |theme: &Theme| if name == "Wk" { dimmed(theme) } else { text::secondary(theme) })
+                    .into()
+            })
+        );
+
+        let grid = Column::with_children(
+            weeks(self.month).into_iter().map(|week| {
+                let number = text(week[0].iso_week().week().to_string())
+                    .size(13)
+                    .center()
+                    .width(CELL_WIDTH)
+                    .height(CELL_HEIGHT)
+                    .style(dimmed);
+
+                Row::with_children(
+                    std::iter::once(number.into()).chain(week.into_iter().map(|day| self.day_view(day, today)))
+                )
+                .into()
+            })
+        );
+
+        let mut children: Vec<Element<'a, crate::Message>> = vec![header.into(), weekdays.into(), grid.into()];
+        if self.calendars {
+            children.push(self.agenda_view());
+        }
+        children.push(space::vertical().into());
+
+        let content = container(
+            Column::with_children(children)
+                .spacing(6)
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding(10)
+        .style(|theme: &Theme| container::Style::default()
+            .background(theme.extended_palette().background.weakest.color)
+            .border(border::rounded(ctx.radius))
+        );
+
+        mouse_area(content)
+            .on_scroll(|delta| Message::Scrolled(delta).into())
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn grid_starts_on_monday() {
+        // October 2026 starts on a thursday and ends on a saturday
+        let weeks = weeks(date(2026, 10, 1));
+
+        assert_eq!(weeks.len(), 5);
+        assert_eq!(weeks[0][0], date(2026, 9, 28));
+        assert_eq!(weeks[4][6], date(2026, 11, 1));
+    }
+
+    #[test]
+    fn iso_week_numbers() {
+        // January 1st 2027 is a friday, so it's in the last week of 2026
+        let weeks = weeks(date(2027, 1, 1));
+
+        assert_eq!(weeks[0][0].iso_week().week(), 53);
+        assert_eq!(weeks[1][0].iso_week().week(), 1);
+    }
+
+    #[test]
+    fn month_starting_on_monday() {
+        // February 2027 starts on a monday, and has exactly four weeks
+        let weeks = weeks(date(2027, 2, 1));
+
+        assert_eq!(weeks.len(), 4);
+        assert_eq!(weeks[0][0], date(2027, 2, 1));
+    }
+
+    #[test]
+    fn shifting_months() {
+        let mut state = State { month: date(2026, 1, 1), ..State::default() };
+
+        state.shift(-1);
+        assert_eq!(state.month, date(2025, 12, 1));
+        state.shift(14);
+        assert_eq!(state.month, date(2027, 2, 1));
+    }
+
+    #[test]
+    fn days_of_occurrences() {
+        let local = |day: u32, hour: u32| midnight(date(2026, 10, day)).unwrap() + TimeDelta::hours(hour.into());
+        let occurrence = |start, end, all_day| Occurrence { summary: String::new(), location: None, start, end, all_day };
+
+        // All day events end at the midnight after their last day
+        let conference = occurrence(local(19, 0), local(22, 0), true);
+        assert_eq!(days(&conference).collect::<Vec<_>>(), vec![date(2026, 10, 19), date(2026, 10, 20), date(2026, 10, 21)]);
+
+        let party = occurrence(local(24, 20), local(25, 2), false);
+        assert_eq!(days(&party).collect::<Vec<_>>(), vec![date(2026, 10, 24), date(2026, 10, 25)]);
+
+        let reminder = occurrence(local(26, 9), local(26, 9), false);
+        assert_eq!(days(&reminder).collect::<Vec<_>>(), vec![date(2026, 10, 26)]);
+    }
+}
@@ -1,2 +1,11 @@
 // The weather popup window
-pub mod weather_window;
\ No newline at end of file
+pub mod weather_window;
+
+// The calendar popup of the clock
+pub mod calendar_window;
+
+// The list of notifications
+pub mod notification_window;
+
+// The notifications of apps, in the corner of the screen
+pub mod toast_window;
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Local};
+use iced::{
+    Alignment, Element, Length, Theme, border,
+    theme::palette::Pair,
+    widget::{Column, Row, button, button::Status, column, container, row, scrollable, text}
+};
+
+use crate::{
+    modules::{Context, notifications::{DndMode, Entry, Message}},
+    notification::{Level, Origin}
+};
+
+pub const SIZE: (u32, u32) = (420, 460);
+
+/// The levels in the order they are listed, the most severe first
+const LEVELS: [Level; 3] = [Level::Error, Level::Warning, Level::Notice];
+
+/// The colors of a level, used for the indicator on the bar too
+pub fn level_pair(theme: &Theme, level: Level) -> Pair {
+    let palette = theme.extended_palette();
+
+    match level {
+        Level::Notice => palette.primary.base,
+        Level::Warning => palette.warning.base,
+        Level::Error => palette.danger.base,
+    }
+}
+
+/// A small text button, disabled without a message
+pub fn action<'a>(label: &'a str, message: Option<Message>, ctx: &'a Context) -> button::Button<'a, crate::Message> {
+    button(text(label).size(13).center())
+        .padding([2, 8])
+        .on_press_maybe(message.map(crate::Message::from))
+        .style(move |theme: &Theme, status: Status| {
+            let palette = theme.extended_palette();
+
+            button::Style {
+                background: Some(match status {
+                    Status::Hovered | Status::Pressed => palette.primary.strong.color,
+                    _ => palette.background.strong.color
+                }.into()),
+                text_color: match status {
+                    Status::Disabled => palette.background.strongest.color,
+                    _ => palette.background.base.text
+                },
+                border: border::rounded(ctx.radius),
+                ..Default::default()
+            }
+        })
+}
+
+fn entry_view<'a>(entry: &'a Entry, ctx: &'a Context) -> Element<'a, crate::Message> {
+    let notification = &entry.notification;
+
+    // Older notifications also have their date
+    let format_time = |time: DateTime<Local>| if time.date_naive() == Local::now().date_naive() {
+        time.format("%H:%M:%S").to_string()
+    } else {
+        time.format("%d %b %H:%M").to_string()
+    };
+
+    // Repeated notifications have the number of times they came, and the last time
+    let time = if entry.count > 1 {
+        format!("{} · ×{}, last at {}", format_time(notification.time), entry.count, format_time(entry.last_seen))
+    } else {
+        format_time(notification.time)
+    };
+
+    // The notifications of apps have the name of the app and their body
+    let (time, body) = match &notification.origin {
+        Origin::App { name, body, .. } => (format!("{name} · {time}"), (!body.is_empty()).then_some(body)),
+        Origin::Bar => (time, None)
+    };
+
+    let retry: Option<Element<'a, crate::Message>> = notification.retryable()
+        .then(|| action("Retry", Some(Message::Retry(entry.id)), ctx).into());
+    let dismiss = action("✕", Some(Message::Dismiss(entry.id)), ctx).into();
+
+    container(
+        row![
+            Column::with_children([
+                text(time).size(11).style(text::secondary).into(),
+                text(&notification.message).size(14).into()
+            ]
+            .into_iter()
+            .chain(body.map(|body| text(body).size(12).style(text::secondary).into())))
+            .spacing(2)
+            .width(Length::Fill),
+            Row::with_children(retry.into_iter().chain([dismiss])).spacing(4)
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center)
+    )
+    .padding(6)
+    .style(|theme: &Theme| container::Style::default()
+        .background(theme.extended_palette().background.weak.color)
+        .border(border::rounded(ctx.radius))
+    )
+    .into()
+}
+
+/// The notifications grouped by level, the newest first in each group
+pub fn view<'a>(entries: &'a [Entry], dnd: DndMode, ctx: &'a Context) -> Element<'a, crate::Message> {
+    let dnd = if dnd == DndMode::Off { "Do not disturb: off" } else { "Do not disturb: on" };
+
+    let header = row![
+        text("Notifications").size(20).width(Length::Fill).style(text::primary),
+        action(dnd, Some(Message::ToggleDnd), ctx),
+        action("Clear all", (!entries.is_empty()).then_some(Message::ClearAll), ctx)
+    ]
+    .spacing(4)
+    .align_y(Alignment::Center);
+
+    let groups = LEVELS.into_iter().filter_map(|level| {
+        let mut group: Vec<&Entry> = entries.iter().filter(|entry| entry.notification.level == level).collect();
+        if group.is_empty() {
+            return None;
+        }
+        group.sort_by_key(|entry| std::cmp::Reverse(entry.last_seen));
+
+        let title = text(format!("{} ({})", level.name(), group.len()))
+            .size(13)
+            .style(move |theme: &Theme| text::Style { color: Some(level_pair(theme, level).color) });
+
+        Some(
+            Column::with_children(
+                std::iter::once(title.into()).chain(group.into_iter().map(|entry| entry_view(entry, ctx)))
+            )
+            .spacing(4)
+            .into()
+        )
+    });
+
+    let list: Element<'a, crate::Message> = if entries.is_empty() {
+        container(text("No notifications").style(text::secondary))
+            .center(Length::Fill)
+            .into()
+    } else {
+        scrollable(Column::with_children(groups).spacing(10).padding([0, 8]))
+            .height(Length::Fill)
+            .into()
+    };
+
+    container(
+        column![header, list].spacing(8)
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(10)
+    .style(|theme: &Theme| container::Style::default()
+        .background(theme.extended_palette().background.weakest.color)
+        .border(border::rounded(ctx.radius))
+    )
+    .into()
+}
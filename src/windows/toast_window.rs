@@ -0,0 +1,117 @@
+use iced::{
+    Alignment, Element, Length, Theme, border,
+    widget::{Column, Row, container, mouse_area, row, text}
+};
+
+use crate::{
+    config::Position,
+    modules::{Context, notifications::{Entry, Message}},
+    notification::Origin,
+    windows::notification_window::{action, level_pair}
+};
+
+pub const WIDTH: u32 = 360;
+/// Toasts have a fixed height, so the surface is sized by their number
+const TOAST_HEIGHT: u32 = 100;
+const SPACING: u32 = 8;
+
+/// The size of the surface showing `toasts` toasts
+pub fn size(toasts: usize) -> (u32, u32) {
+    let toasts = toasts as u32;
+    (WIDTH, toasts * TOAST_HEIGHT + toasts.saturating_sub(1) * SPACING)
+}
+
+fn toast_view<'a>(entry: &'a Entry, ctx: &'a Context) -> Element<'a, crate::Message> {
+    let notification = &entry.notification;
+    let level = notification.level;
+
+    let (source, body, actions) = match &notification.origin {
+        Origin::App { name, body, actions, .. } => (name.as_str(), body.as_str(), actions.as_slice()),
+        Origin::Bar => (level.name(), "", [].as_slice())
+    };
+
+    // The default action is invoked by clicking the toast, like in other servers
+    let default = actions.iter().find(|(key, _)| key == "default");
+    let retry: Option<Element<'a, crate::Message>> = notification.retryable()
+        .then(|| action("Retry", Some(Message::Retry(entry.id)), ctx).into());
+    let buttons: Vec<Element<'a, crate::Message>> = actions
+        .iter()
+        .filter(|(key, _)| key != "default")
+        .map(|(key, label)| action(label, Some(Message::Action(entry.id, key.clone())), ctx).into())
+        .chain(retry)
+        .collect();
+
+    let time = match entry.count {
+        1 => entry.last_seen.format("%H:%M").to_string(),
+        count => format!("×{count} · {}", entry.last_seen.format("%H:%M"))
+    };
+
+    let header = row![
+        text(source).size(12).width(Length::Fill).style(text::secondary),
+        text(time).size(12).style(text::secondary),
+        action("✕", Some(Message::CloseToast(entry.id)), ctx)
+    ]
+    .spacing(6)
+    .align_y(Alignment::Center);
+
+    let message: Column<'a, crate::Message> = Column::with_children([text(&notification.message).size(15).into()]
+        .into_iter()
+        .chain((!body.is_empty()).then(|| text(body).size(13).style(text::secondary).into())))
+        .spacing(2);
+
+    // Only the text is clipped, so a long body can't push the buttons out of the toast
+    let content = Column::with_children([
+        header.into(),
+        container(message).height(Length::Fill).clip(true).into(),
+    ]
+    .into_iter()
+    .chain((!buttons.is_empty()).then(|| Row::with_children(buttons).spacing(4).into())))
+    .spacing(2);
+
+    let card = container(content)
+        .width(Length::Fill)
+        .height(TOAST_HEIGHT as f32)
+        .padding(8)
+        .clip(true)
+        .style(move |theme: &Theme| container::Style::default()
+            .background(theme.extended_palette().background.base.color)
+            .border(border::rounded(ctx.radius).width(2).color(level_pair(theme, level).color))
+        );
+
+    // The toast doesn't expire while it's hovered
+    let area = mouse_area(card)
+        .on_enter(Message::ToastEntered(entry.id).into())
+        .on_exit(Message::ToastLeft(entry.id).into());
+
+    match default {
+        Some((key, _)) => area.on_press(Message::Action(entry.id, key.clone()).into()).into(),
+        None => area.into()
+    }
+}
+
+/// The toasts stacked from the edge of the screen, `entries` come the newest first
+pub fn view<'a>(entries: impl Iterator<Item = &'a Entry>, ctx: &'a Context) -> Element<'a, crate::Message> {
+    let mut toasts: Vec<Element<'a, crate::Message>> = entries.map(|entry| toast_view(entry, ctx)).collect();
+
+    // The toasts are in the bottom corner with a bottom bar
+    if ctx.position == Position::Bottom {
+        toasts.reverse();
+    }
+
+    Column::with_children(toasts)
+        .spacing(SPACING)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surface_fits_the_toasts() {
+        assert_eq!(size(1), (WIDTH, TOAST_HEIGHT));
+        assert_eq!(size(3), (WIDTH, 3 * TOAST_HEIGHT + 2 * SPACING));
+    }
+}
@@ -1,5 +1,3 @@
-use std::str::FromStr;
-
 use iced::{Alignment, Border, Color, Element, Length, Renderer, Task, Theme, border::{self, radius, rounded}, widget::{ Row, button::Status, column, container, row, scrollable::{Direction, Scrollbar}, space, svg, tooltip}};
 use iced::widget::{button, scrollable, Button, text};
 use iced::widget::text::LineHeight;
@@ -7,7 +5,12 @@ use iced_layershell::{
     to_layer_message
 };
 
-use crate::{get_svg, graph::{Series, graph}};
+use crate::{
+    get_svg,
+    graph::{Series, graph},
+    modules::{Context, weather::{self, Weather}},
+    weather::{HourlyWeather, prelude::{Humidity, Precipitation, Temperature, Wind}}
+};
 
 /// The display mode of some data
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -40,6 +43,17 @@ fn get_button_color(theme: &Theme, status: Status) -> Color {
     }
 }
 
+/// The value formatted, or a placeholder if the api left it out
+fn or_missing<T>(value: Option<&T>, format: impl Fn(&T) -> String) -> String {
+    value.map(format).unwrap_or_else(|| weather::MISSING.to_string())
+}
+
+/// A series of the hourly values, None if the api left out any of them
+fn hourly_series<T: Into<f32>>(color: Color, hourly: &[HourlyWeather], value: impl Fn(&HourlyWeather) -> Option<T>) -> Option<Series> {
+    let values: Option<Vec<f32>> = hourly.iter().map(|hour| value(hour).map(Into::into)).collect();
+    values.map(|values| Series::evenly_distribute(color, values))
+}
+
 /// Builds a new button with a svg to the given parameters
 fn svg_button_builder<'a>(
     svg_handle: svg::Handle, 
@@ -97,7 +111,7 @@ impl State {
         }
     }
 
-    pub fn view<'a>(&'a self, state: &'a crate::State) -> Element<'a, crate::Message> {
+    pub fn view<'a>(&'a self, weather: &'a Weather, ctx: &'a Context) -> Element<'a, crate::Message> {
         let padding = 5;
 
         // Common svgs
@@ -114,20 +128,20 @@ impl State {
         // Current weather
         let current_height = 120;
         let current = {
-            container(match &state.weather_current{
+            container(match &weather.current{
                 Some(weather) => {
-                    let code_svg_handle = svg::Handle::from_memory(
-                        get_svg(
-                            if weather.is_day.unwrap() {"day"} else {"night"},
-                            weather.code.as_ref().unwrap().get_svg_name().as_str()
-                        ).as_bytes()
-                    );
+                    let code_svg_handle = weather::icon(weather);
+
+                    let code_string = weather.code
+                        .as_ref()
+                        .map(|code| code.to_string())
+                        .unwrap_or_else(|| String::from("Unknown weather"));
 
                     let code_string_size = 
-                        if weather.code.as_ref().unwrap().to_string().chars().count() < 15 {
+                        if code_string.chars().count() < 15 {
                             32
                         }
-                        else if weather.code.as_ref().unwrap().to_string().chars().count() > 23 {
+                        else if code_string.chars().count() > 23 {
                             28
                         } else {
                             24
@@ -142,13 +156,13 @@ impl State {
                             column![
                                 row![
                                     column![
-                                        text(weather.temperature.as_ref().unwrap().stringify())
+                                        text(or_missing(weather.temperature.as_ref(), Temperature::stringify))
                                             .size(60)
                                             .style(text::primary)
                                             .align_y(Alignment::Start)
                                             .line_height(LineHeight::Relative(0.8))
                                         ,
-                                        text(format!("Feels like {}", weather.apparent_temperature.as_ref().unwrap().stringify()))
+                                        text(format!("Feels like {}", or_missing(weather.apparent_temperature.as_ref(), Temperature::stringify)))
                                             .size(18)
                                             .style(text::secondary)
                                             .line_height(LineHeight::Relative(0.9)),
@@ -156,7 +170,7 @@ impl State {
                                     space::horizontal(),
                                     column![
                                         row![
-                                            text(weather.precipitation.as_ref().unwrap().combined_to_string())
+                                            text(or_missing(weather.precipitation.as_ref(), Precipitation::combined_to_string))
                                                 .size(18)
                                                 .style(text::primary)
                                                 .align_y(Alignment::Center),
@@ -165,7 +179,7 @@ impl State {
                                                 .height(18)
                                         ],
                                         row![
-                                            text(weather.humidity.as_ref().unwrap().stringify())
+                                            text(or_missing(weather.humidity.as_ref(), Humidity::stringify))
                                                 .size(18)
                                                 .style(text::primary)
                                                 .align_y(Alignment::Center),
@@ -174,7 +188,7 @@ impl State {
                                                 .height(18)
                                         ],
                                         row![
-                                            text(weather.wind.as_ref().unwrap().stringify())
+                                            text(or_missing(weather.wind.as_ref(), Wind::stringify))
                                                 .size(18)
                                                 .style(text::primary)
                                                 .align_y(Alignment::Center),
@@ -187,7 +201,7 @@ impl State {
                                 ],
                                 space::vertical(),
                                 // Lower text
-                                text(weather.code.as_ref().unwrap().to_string())
+                                text(code_string)
                                     .size(code_string_size)
                                     .style(text::primary)
                             ]
@@ -228,7 +242,7 @@ impl State {
             .padding(padding as u16)
             .style(|theme: &Theme| container::Style::default()
                 .background(theme.extended_palette().background.strong.color)
-                .border(rounded(state.radius))
+                .border(rounded(ctx.radius))
             )
         };
 
@@ -260,7 +274,7 @@ impl State {
                                 background: if self.display_mode == DisplayMode::Cards {Some(palette.primary.strong.color.into())} else {Some(palette.secondary.base.color.into())},
                                 border: iced::Border { 
                                     width: 5.0, 
-                                    radius: border::Radius { top_left: state.radius as f32, top_right: 0.0, bottom_right: 0.0, bottom_left: state.radius as f32 },
+                                    radius: border::Radius { top_left: ctx.radius as f32, top_right: 0.0, bottom_right: 0.0, bottom_left: ctx.radius as f32 },
                                     ..Default::default()
                                 },
                                 ..Default::default()
@@ -268,7 +282,7 @@ impl State {
                         })
                         .on_press_maybe({
                             if self.display_mode != DisplayMode::Cards {
-                                Some(crate::Message::WeatherWindowMessage(Message::DisplayModeChange(DisplayMode::Cards)))
+                                Some(crate::Message::from(Message::DisplayModeChange(DisplayMode::Cards)))
                             } else {
                                 None
                             }
@@ -298,7 +312,7 @@ impl State {
                                 background: if self.display_mode == DisplayMode::Graph {Some(palette.primary.strong.color.into())} else {Some(palette.secondary.base.color.into())},
                                 border: iced::Border { 
                                     width: 5.0, 
-                                    radius: border::Radius { top_left: 0.0, top_right: state.radius as f32, bottom_right: state.radius as f32, bottom_left: 0.0 },
+                                    radius: border::Radius { top_left: 0.0, top_right: ctx.radius as f32, bottom_right: ctx.radius as f32, bottom_left: 0.0 },
                                     ..Default::default()
                                 },
                                 ..Default::default()
@@ -306,7 +320,7 @@ impl State {
                         })
                         .on_press_maybe({
                             if self.display_mode != DisplayMode::Graph {
-                                Some(crate::Message::WeatherWindowMessage(Message::DisplayModeChange(DisplayMode::Graph)))
+                                Some(crate::Message::from(Message::DisplayModeChange(DisplayMode::Graph)))
                             } else {
                                 None
                             }
@@ -329,7 +343,7 @@ impl State {
                         background: Some(get_button_color(theme, status).into()),
                         border: Border {
                             radius: border::Radius::new(0.0)
-                                .right(state.radius as f32),
+                                .right(ctx.radius as f32),
                             ..Default::default()
                         },
                         ..Default::default()
@@ -341,7 +355,7 @@ impl State {
                         background: Some(get_button_color(theme, status).into()),
                         border: Border {
                             radius: border::Radius::new(0.0)
-                                .left(state.radius as f32),
+                                .left(ctx.radius as f32),
                             ..Default::default()
                         },
                         ..Default::default()
@@ -359,7 +373,7 @@ impl State {
                                         navbar_height as f32,
                                         (navbar_height - 4) as f32,
                                         left_button_style,
-                                        crate::Message::WeatherWindowMessage(Message::GraphTypeChange(Some(GraphType::Temp)))
+                                        crate::Message::from(Message::GraphTypeChange(Some(GraphType::Temp)))
                                     ),
 
                                     // PrecProb
@@ -368,7 +382,7 @@ impl State {
                                         navbar_height as f32,
                                         (navbar_height - 4) as f32,
                                         middle_button_style,
-                                        crate::Message::WeatherWindowMessage(Message::GraphTypeChange(Some(GraphType::PrecProb)))
+                                        crate::Message::from(Message::GraphTypeChange(Some(GraphType::PrecProb)))
                                     ),
 
                                     // Prec
@@ -377,7 +391,7 @@ impl State {
                                         navbar_height as f32,
                                         (navbar_height - 4) as f32,
                                         middle_button_style,
-                                        crate::Message::WeatherWindowMessage(Message::GraphTypeChange(Some(GraphType::Prec)))
+                                        crate::Message::from(Message::GraphTypeChange(Some(GraphType::Prec)))
                                     ),
 
                                     // Wind
@@ -386,7 +400,7 @@ impl State {
                                         navbar_height as f32,
                                         (navbar_height - 4) as f32,
                                         right_button_style,
-                                        crate::Message::WeatherWindowMessage(Message::GraphTypeChange(Some(GraphType::Wind)))
+                                        crate::Message::from(Message::GraphTypeChange(Some(GraphType::Wind)))
                                     )
                                 ]
                                 .align_y(Alignment::Center)
@@ -401,7 +415,7 @@ impl State {
                                     navbar_height as f32,
                                         (navbar_height - 4) as f32, 
                                         right_button_style, 
-                                        crate::Message::WeatherWindowMessage(Message::GraphTypeChange(None))
+                                        crate::Message::from(Message::GraphTypeChange(None))
                                 );
                                 use GraphType::*;
                                 match graph_type {
@@ -423,7 +437,7 @@ impl State {
                                                                 ),
                                                                 border: Border {
                                                                     radius: radius(0)
-                                                                        .left(state.radius as f32),
+                                                                        .left(ctx.radius as f32),
                                                                     ..Default::default()
                                                                 },
                                                                 ..Default::default()
@@ -486,7 +500,7 @@ impl State {
                                                                 ),
                                                                 border: Border {
                                                                     radius: radius(0)
-                                                                        .left(state.radius as f32),
+                                                                        .left(ctx.radius as f32),
                                                                     ..Default::default()
                                                                 },
                                                                 ..Default::default()
@@ -601,10 +615,10 @@ impl State {
                                                     (navbar_height - 4) as f32, 
                                                     |theme: &Theme, status: Status| button::Style {
                                                         background: Some(get_button_color(theme, status).into()),
-                                                        border: rounded(state.radius),
+                                                        border: rounded(ctx.radius),
                                                         ..Default::default()
                                                     },
-                                                    crate::Message::WeatherWindowMessage(Message::GraphTypeChange(None))
+                                                    crate::Message::from(Message::GraphTypeChange(None))
                                             ).into()
                                         )
                                     }
@@ -631,7 +645,7 @@ impl State {
 
         let hourly_height = 150;
         let hourly_body: container::Container<'_, crate::Message, Theme, Renderer> = { container(
-                if state.weather_hourly.is_empty() {
+                if weather.hourly.is_empty() {
                         let row_thingy: Element<'_, crate::Message, Theme, Renderer> = row![
                             space::horizontal(),
                             text("Refresh hourly weather data")
@@ -653,7 +667,7 @@ impl State {
                         )
                             .width(Length::Fill)
                             .height(Length::Fill)
-                            .on_press(crate::Message::ParseHourlyWeather)
+                            .on_press(crate::Message::from(weather::Message::ParseHourly))
                             .style(|_, _| button::Style {
                                 background: Some(Color::TRANSPARENT.into()),
                                 ..Default::default()
@@ -680,7 +694,7 @@ impl State {
                                     select_type
                                 },
                                 Some(graph_type) => {
-                                    let mut series: Vec<Series> = Vec::new();
+                                    let mut series: Vec<Option<Series>> = Vec::new();
 
                                     let mut min: Option<f32> = None;
                                     let mut max: Option<f32> = None;
@@ -689,29 +703,17 @@ impl State {
                                     use GraphType::*;
                                     match graph_type {
                                         Temp => {
-                                            series.push(
-                                                Series::evenly_distribute(
-                                                    state.theme.as_ref().unwrap().extended_palette().success.base.color,
-                                                    state.weather_hourly
-                                                        .iter()
-                                                        .map(|hour| {
-                                                            hour.temperature.as_ref().unwrap().temp
-                                                        })
-                                                        .collect()
-                                                )
-                                            );
-
-                                            series.push(
-                                                Series::evenly_distribute(
-                                                    state.theme.as_ref().unwrap().extended_palette().warning.base.color,
-                                                    state.weather_hourly
-                                                        .iter()
-                                                        .map(|hour| {
-                                                            hour.apparent_temperature.as_ref().unwrap().temp
-                                                        })
-                                                        .collect()   
-                                                )
-                                            );
+                                            series.push(hourly_series(
+                                                ctx.theme.extended_palette().success.base.color,
+                                                &weather.hourly,
+                                                |hour| hour.temperature.as_ref().map(|temperature| temperature.temp)
+                                            ));
+
+                                            series.push(hourly_series(
+                                                ctx.theme.extended_palette().warning.base.color,
+                                                &weather.hourly,
+                                                |hour| hour.apparent_temperature.as_ref().map(|temperature| temperature.temp)
+                                            ));
                                         },
 
                                         PrecProb => {
@@ -719,65 +721,37 @@ impl State {
                                             max = Some(100.0);
                                             steps = Some(20);
 
-                                            series.push(
-                                                Series::evenly_distribute(
-                                                    state.theme.as_ref().unwrap().extended_palette().success.base.color,
-                                                    state.weather_hourly
-                                                        .iter()
-                                                        .map(|hour| hour.precipitation.as_ref().unwrap().probability.unwrap())
-                                                        .collect()
-                                                )
-                                            )
+                                            series.push(hourly_series(
+                                                ctx.theme.extended_palette().success.base.color,
+                                                &weather.hourly,
+                                                |hour| hour.precipitation.as_ref().and_then(|precipitation| precipitation.probability)
+                                            ))
                                         }, 
 
                                         Prec => {
-                                            series.push(
-                                                Series::evenly_distribute(
-                                                    state.theme.as_ref().unwrap().extended_palette().success.base.color,
-                                                    state.weather_hourly
-                                                        .iter()
-                                                        .map(|hour| {
-                                                            hour.precipitation.as_ref().unwrap().combined.unwrap()
-                                                        })
-                                                        .collect()
-                                                )
-                                            );
-
-                                            series.push(
-                                                Series::evenly_distribute(
-                                                    state.theme.as_ref().unwrap().extended_palette().warning.base.color,
-                                                    state.weather_hourly
-                                                        .iter()
-                                                        .map(|hour| {
-                                                            hour.precipitation.as_ref().unwrap().rain.unwrap()
-                                                        })
-                                                        .collect()
-                                                )
-                                            );
-
-                                            series.push(
-                                                Series::evenly_distribute(
-                                                    state.theme.as_ref().unwrap().extended_palette().danger.base.color,
-                                                    state.weather_hourly
-                                                        .iter()
-                                                        .map(|hour| {
-                                                            hour.precipitation.as_ref().unwrap().showers.unwrap()
-                                                        })
-                                                        .collect()
-                                                )
-                                            );
-
-                                            series.push(
-                                                Series::evenly_distribute(
-                                                    state.theme.as_ref().unwrap().extended_palette().primary.base.color,
-                                                    state.weather_hourly
-                                                        .iter()
-                                                        .map(|hour| {
-                                                            hour.precipitation.as_ref().unwrap().snowfall.unwrap()
-                                                        })
-                                                        .collect()
-                                                )
-                                            );
+                                            series.push(hourly_series(
+                                                ctx.theme.extended_palette().success.base.color,
+                                                &weather.hourly,
+                                                |hour| hour.precipitation.as_ref().and_then(|precipitation| precipitation.combined)
+                                            ));
+
+                                            series.push(hourly_series(
+                                                ctx.theme.extended_palette().warning.base.color,
+                                                &weather.hourly,
+                                                |hour| hour.precipitation.as_ref().and_then(|precipitation| precipitation.rain)
+                                            ));
+
+                                            series.push(hourly_series(
+                                                ctx.theme.extended_palette().danger.base.color,
+                                                &weather.hourly,
+                                                |hour| hour.precipitation.as_ref().and_then(|precipitation| precipitation.showers)
+                                            ));
+
+                                            series.push(hourly_series(
+                                                ctx.theme.extended_palette().primary.base.color,
+                                                &weather.hourly,
+                                                |hour| hour.precipitation.as_ref().and_then(|precipitation| precipitation.snowfall)
+                                            ));
                                         },
 
                                         Wind => {
@@ -785,55 +759,57 @@ impl State {
                                             max = Some(100.0);
                                             steps = Some(20);
 
-                                            series.push(
-                                                Series::evenly_distribute(
-                                                    state.theme.as_ref().unwrap().extended_palette().success.base.color,
-                                                    state.weather_hourly
-                                                        .iter()
-                                                        .map(|hour| hour.wind.as_ref().unwrap().speed.unwrap())
-                                                        .collect()
-                                                )
-                                            )
+                                            series.push(hourly_series(
+                                                ctx.theme.extended_palette().success.base.color,
+                                                &weather.hourly,
+                                                |hour| hour.wind.as_ref().and_then(|wind| wind.speed)
+                                            ))
                                         }
                                     }
 
-                                    let labels = state.weather_hourly
+                                    let labels = weather.hourly
                                         .iter()
                                         .step_by(2)
                                         .map(|hour| hour.time.format("%H").to_string())
                                         .collect::<Vec<String>>();
 
-                                    graph(
-                                        state.theme.as_ref().unwrap().extended_palette().background.strongest.color,
-                                        2.0,
-                                        15.0,
-                                        Color::from_str("#cba6f7").unwrap(),
-                                        labels,
-                                        35.0,
-                                        series,
-                                        min,
-                                        max,
-                                        steps,
-                                        3.0,
-                                        None
-                                    )
-                                    .width(Length::Fill)
-                                    .height(hourly_height as f32,)
-                                    .into()
+                                    // Holes would be plotted as 0, so the graph is hidden instead
+                                    match series.into_iter().collect::<Option<Vec<Series>>>() {
+                                        Some(series) => graph(
+                                            ctx.theme.extended_palette().background.strongest.color,
+                                            2.0,
+                                            15.0,
+                                            Color::from_rgb8(0xcb, 0xa6, 0xf7),
+                                            labels,
+                                            35.0,
+                                            series,
+                                            min,
+                                            max,
+                                            steps,
+                                            3.0,
+                                            None
+                                        )
+                                        .width(Length::Fill)
+                                        .height(hourly_height as f32,)
+                                        .into(),
+                                        None => container(
+                                            text(weather::MISSING)
+                                                .style(text::secondary)
+                                        )
+                                        .width(Length::Fill)
+                                        .height(Length::Fill)
+                                        .align_x(Alignment::Center)
+                                        .align_y(Alignment::Center)
+                                        .into()
+                                    }
                                 }
                             }
                         },
                         DisplayMode::Cards => {
                             let mut cards = Vec::new();
 
-                            for hour in &state.weather_hourly {
-                                let code_svg_handle = svg::Handle::from_memory(
-                                        get_svg(
-                                            if hour.is_day.unwrap() {"day"} else {"night"}, 
-                                            hour.code.as_ref().unwrap().get_svg_name().as_str()
-                                        )
-                                        .as_bytes()
-                                );
+                            for hour in &weather.hourly {
+                                let code_svg_handle = weather::icon(hour);
 
                                 cards.push(
                                     container(
@@ -845,7 +821,7 @@ impl State {
                                             svg(code_svg_handle)
                                                 .width(58)
                                                 .height(58),
-                                            text(hour.temperature.as_ref().unwrap().stringify())
+                                            text(or_missing(hour.temperature.as_ref(), Temperature::stringify))
                                                 .align_x(Alignment::Center)
                                                 .style(text::primary)
                                                 .line_height(LineHeight::Relative(0.95))
@@ -854,7 +830,7 @@ impl State {
                                                 svg(droplet.clone())
                                                     .width(10)
                                                     .height(10),
-                                                text(hour.precipitation.as_ref().unwrap().combined_to_string())
+                                                text(or_missing(hour.precipitation.as_ref(), Precipitation::combined_to_string))
                                                     .align_x(Alignment::Center)
                                                     .style(text::secondary)
                                                     .line_height(LineHeight::Relative(0.9))
@@ -864,7 +840,7 @@ impl State {
                                                 svg(wind.clone())
                                                     .width(10)
                                                     .height(10),
-                                                text(hour.wind.as_ref().unwrap().speed_stringify())
+                                                text(or_missing(hour.wind.as_ref(), Wind::speed_stringify))
                                                     .align_x(Alignment::Center)
                                                     .style(text::secondary)
                                                     .line_height(LineHeight::Relative(0.9))
@@ -879,7 +855,7 @@ impl State {
                                         .align_x(Alignment::Center)
                                         .style(|theme: &Theme| container::Style::default()
                                             .background(theme.extended_palette().background.strong.color)
-                                            .border(border::rounded(state.radius))
+                                            .border(border::rounded(ctx.radius))
                                         )
                                     .into()
                                 );
@@ -888,7 +864,7 @@ impl State {
                             scrollable(
                                 Row::from_vec(cards)
                                     .height(hourly_height)
-                                    .spacing(state.spacing)
+                                    .spacing(ctx.spacing)
                             )
                                 .height(Length::Fill)
                                 .width(Length::Fill)
@@ -915,9 +891,9 @@ impl State {
                 .width(bottom_bar_height)
                 .height(bottom_bar_height)
                 .style(button::primary)
-                .on_press(crate::Message::ParseWeather),
+                .on_press(crate::Message::from(weather::Message::Parse)),
                 space::horizontal(),
-                text(match &state.weather_current {
+                text(match &weather.current {
                     Some(weather) => format!("Parsed for: {}; {}", weather.coordinates.lng, weather.coordinates.lat),
                     None => String::new()
                 })
@@ -944,7 +920,7 @@ impl State {
         .padding(5)
         .style(|theme: &Theme| container::Style::default()
             .background(theme.extended_palette().background.weakest.color)
-            .border(border::rounded(state.radius))
+            .border(border::rounded(ctx.radius))
         )
         .into()
     }